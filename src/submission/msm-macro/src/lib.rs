@@ -11,6 +11,7 @@ struct SplitterSpec {
     name: Ident,
     input: SplitterInputSpec,
     output: LitInt,
+    signed: bool,
 }
 
 struct SplitterInputSpec {
@@ -41,138 +42,280 @@ impl Parse for SplitterSpec {
 
         input.parse::<Token![->]>()?;
 
-        let output;
-        let _ = bracketed!(output in input);
-        let output = output.parse::<LitInt>()?;
+        let output_spec;
+        let _ = bracketed!(output_spec in input);
+        let output = output_spec.parse::<LitInt>()?;
+        let signed = if output_spec.is_empty() {
+            false
+        } else {
+            let keyword = output_spec.parse::<Ident>()?;
+            if keyword != "signed" {
+                return Err(syn::Error::new(keyword.span(), "expected `signed`"));
+            }
+            true
+        };
 
         Ok(Self {
             name,
             input: input_spec,
             output,
+            signed,
         })
     }
 }
 
-fn get_unsigned_type_width(ty: &Type) -> usize {
-    match ty {
-        Type::Path(path) if path.qself.is_none() && path.path.segments.len() == 1 => {
-            let segment = &path.path.segments[0];
-            match &segment.ident.to_string()[..] {
-                "u8" => 8usize,
-                "u16" => 16usize,
-                "u32" => 32usize,
-                "u64" => 64usize,
-                "u128" => 128usize,
-                _ => panic!("Expected unsigned integer type"),
+fn get_unsigned_type_width(ty: &Type) -> syn::Result<usize> {
+    if let Type::Path(path) = ty {
+        if path.qself.is_none() && path.path.segments.len() == 1 {
+            match &path.path.segments[0].ident.to_string()[..] {
+                "u8" => return Ok(8),
+                "u16" => return Ok(16),
+                "u32" => return Ok(32),
+                "u64" => return Ok(64),
+                "u128" => return Ok(128),
+                _ => {}
             }
         }
-        _ => panic!("Expected unsigned integer type"),
     }
+    Err(syn::Error::new_spanned(
+        ty,
+        "Expected unsigned integer type",
+    ))
+}
+
+/// Generates an expression for the `i`-th window (0 is the least significant)
+/// of the big-endian input, as `output_type`.
+fn window_expr(
+    i: usize,
+    window_size: usize,
+    input_limb_bitwidth: usize,
+    n_input_limbs: usize,
+    input_tok: &proc_macro2::TokenStream,
+    output_type: &Type,
+) -> proc_macro2::TokenStream {
+    // Least significant input bit to contribute to this window.
+    let bit_start = i * window_size;
+    // Least significant input limb to contribute to this window.
+    let limb_start = bit_start / input_limb_bitwidth;
+    // One-past-the-most-significant input bit to contribute to this window.
+    let bit_end = (i + 1) * window_size;
+    // Most significant input limb to contribute to this window.
+    let limb_end = ((bit_end - 1) / input_limb_bitwidth).min(n_input_limbs - 1);
+
+    let mut expr = quote! {};
+    expr.append_separated(
+        (limb_start..=limb_end).map(|j| {
+            // The least significant `unused_part` bits of the `j`-th
+            // limb are not used to construct the ith window.
+            let unused_part = if j == limb_start {
+                bit_start - j * input_limb_bitwidth
+            } else {
+                0
+            };
+            // The least significant `used_part` bits, excluding the
+            // unused part above, are used to construct the ith window.
+            let used_part = if j == limb_end {
+                (bit_end - j * input_limb_bitwidth).min(input_limb_bitwidth)
+            } else {
+                input_limb_bitwidth
+            };
+            // Index of the `j`-th limb in the input. Assumes big-endian.
+            let idx = n_input_limbs - 1 - j;
+            // Shift out the unused part on the right.
+            let shifted = if unused_part == 0 {
+                quote! { #input_tok[#idx] }
+            } else {
+                quote! { (#input_tok[#idx] >> #unused_part) }
+            };
+            // Mask out the unused part on the left.
+            let masked = if used_part == input_limb_bitwidth {
+                shifted
+            } else {
+                let mask = format!(
+                    "0x{:x}u{}",
+                    (1u64 << (used_part - unused_part)) - 1,
+                    input_limb_bitwidth
+                );
+                let mask = LitInt::new(&mask, Span::call_site());
+                quote! { (#shifted & #mask) }
+            };
+
+            let left_shift = j * input_limb_bitwidth + unused_part - bit_start;
+            if left_shift == 0 {
+                quote! { #masked as #output_type }
+            } else {
+                quote! { ((#masked as #output_type) << #left_shift) }
+            }
+        }),
+        quote! { | },
+    );
+    expr
 }
 
 #[proc_macro]
 pub fn define_msm_scalar_splitter(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as SplitterSpec);
+    expand_splitter(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-    let window_size = input.output.base10_parse::<u8>().unwrap();
+fn expand_splitter(input: SplitterSpec) -> syn::Result<proc_macro2::TokenStream> {
+    let window_size = input.output.base10_parse::<u8>()?;
+    if window_size == 0 {
+        return Err(syn::Error::new_spanned(
+            &input.output,
+            "Window size must be positive",
+        ));
+    }
     let output_type = match input.output.suffix() {
         "u8" => parse_quote! { u8 },
         "u16" => parse_quote! { u16 },
         "u32" => parse_quote! { u32 },
         "u64" => parse_quote! { u64 },
         "u128" => parse_quote! { u128 },
+        "i8" => parse_quote! { i8 },
+        "i16" => parse_quote! { i16 },
+        "i32" => parse_quote! { i32 },
+        "i64" => parse_quote! { i64 },
+        "i128" => parse_quote! { i128 },
         "" => input.input.limb_type.clone(),
-        _ => panic!("Unsupported output type"),
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.output,
+                "Unsupported output type",
+            ))
+        }
     };
-    let input_limb_bitwidth = get_unsigned_type_width(&input.input.limb_type);
-    let n_input_limbs = input.input.n_limbs.base10_parse::<usize>().unwrap();
+    if input.signed != input.output.suffix().starts_with('i') {
+        return Err(syn::Error::new_spanned(
+            &input.output,
+            "Signed windows must use a signed output type and vice versa",
+        ));
+    }
+    let input_limb_bitwidth = get_unsigned_type_width(&input.input.limb_type)?;
+    let n_input_limbs = input.input.n_limbs.base10_parse::<usize>()?;
     let n_total_input_bits = n_input_limbs * input_limb_bitwidth;
-    let n_windows = (n_total_input_bits + window_size as usize - 1) / window_size as usize;
 
-    let mut body = quote! {};
     let input_tok = quote! { input };
-
-    body.append_separated(
-        (0..n_windows).rev().map(|i| {
-            // The ith window. 0 is the least significant and n_windows - 1 is the most significant.
-
-            // Least significant input bit to contribute to this window.
-            let bit_start = i * window_size as usize;
-            // Least significant input limb to contribute to this window.
-            let limb_start = bit_start / input_limb_bitwidth;
-            // One-past-the-most-significant input bit to contribute to this window.
-            let bit_end = (i + 1) * window_size as usize;
-            // Most significant input limb to contribute to this window.
-            let limb_end = ((bit_end - 1) / input_limb_bitwidth).min(n_input_limbs - 1);
-
-            let mut expr = quote! {};
-            expr.append_separated(
-                (limb_start..=limb_end).map(|j| {
-                    // The least significant `unused_part` bits of the `j`-th
-                    // limb are not used to construct the ith window.
-                    let unused_part = if j == limb_start {
-                        bit_start - j * input_limb_bitwidth
-                    } else {
-                        0
-                    };
-                    // The least significant `used_part` bits, excluding the
-                    // unused part above, are used to construct the ith window.
-                    let used_part = if j == limb_end {
-                        (bit_end - j * input_limb_bitwidth).min(input_limb_bitwidth)
-                    } else {
-                        input_limb_bitwidth
-                    };
-                    // Index of the `j`-th limb in the input. Assumes big-endian.
-                    let idx = n_input_limbs - 1 - j;
-                    // Shift out the unused part on the right.
-                    let shifted = if unused_part == 0 {
-                        quote! { #input_tok[#idx] }
-                    } else {
-                        quote! { (#input_tok[#idx] >> #unused_part) }
-                    };
-                    // Mask out the unused part on the left.
-                    let masked = if used_part == input_limb_bitwidth {
-                        shifted
-                    } else {
-                        let mask = format!(
-                            "0x{:x}u{}",
-                            (1u64 << (used_part - unused_part)) - 1,
-                            input_limb_bitwidth
-                        );
-                        let mask = LitInt::new(&mask, Span::call_site());
-                        quote! { (#shifted & #mask) }
-                    };
-
-                    let left_shift = j * input_limb_bitwidth + unused_part - bit_start;
-                    if left_shift == 0 {
-                        quote! { #masked as #output_type }
-                    } else {
-                        quote! { ((#masked as #output_type) << #left_shift) }
-                    }
-                }),
-                quote! { | },
-            );
-            expr
-        }),
-        quote! { , },
-    );
-
     let input_type = input.input.limb_type;
     let input_size = input.input.n_limbs;
     let name = input.name;
+
+    if !input.signed {
+        let output_bitwidth = match input.output.suffix() {
+            "" => input_limb_bitwidth,
+            suffix => suffix[1..].parse::<usize>().unwrap(),
+        };
+        if window_size as usize > output_bitwidth {
+            return Err(syn::Error::new_spanned(
+                &input.output,
+                "Window size too large for the output type",
+            ));
+        }
+        let n_windows = (n_total_input_bits + window_size as usize - 1) / window_size as usize;
+        let mut body = quote! {};
+        body.append_separated(
+            (0..n_windows).rev().map(|i| {
+                window_expr(
+                    i,
+                    window_size as usize,
+                    input_limb_bitwidth,
+                    n_input_limbs,
+                    &input_tok,
+                    &output_type,
+                )
+            }),
+            quote! { , },
+        );
+
+        let expanded = quote! {
+            pub(crate) struct #name;
+
+            impl SplitImpl for #name {
+                const WINDOW_SIZE: usize = (#window_size) as usize;
+                const N_WINDOWS: usize = #n_windows;
+                type Output = #output_type;
+
+                fn split(#input_tok: &[#input_type; #input_size]) -> [#output_type; #n_windows] {
+                    [ #body ]
+                }
+            }
+        };
+
+        return Ok(expanded);
+    }
+
+    // Signed digits are in [-2^(w-1), 2^(w-1)]. Recoding can carry one bit
+    // past the most significant input bit, so we may need an extra window.
+    let n_windows = (n_total_input_bits + window_size as usize) / window_size as usize;
+    let n_unsigned_windows = (n_total_input_bits + window_size as usize - 1) / window_size as usize;
+    let output_bitwidth = input.output.suffix()[1..].parse::<usize>().unwrap();
+    // The digit before subtracting the carry can be as large as 2^w.
+    if window_size as usize + 1 >= output_bitwidth {
+        return Err(syn::Error::new_spanned(
+            &input.output,
+            "Window size too large for the output type",
+        ));
+    }
+
+    let half = LitInt::new(
+        &format!("{}{}", 1u128 << (window_size - 1), input.output.suffix()),
+        Span::call_site(),
+    );
+    let shift = output_bitwidth - 1;
+    let digit = |i: usize| Ident::new(&format!("d{}", i), Span::call_site());
+    let carry = |i: usize| Ident::new(&format!("c{}", i), Span::call_site());
+
+    // Propagate carries from the least significant window upwards. The carry
+    // out of each window is the sign bit of `half - digit`, i.e., 1 iff
+    // `digit > half`, which keeps the generated code branch-free.
+    let mut body = quote! {};
+    for i in 0..n_windows {
+        let d = digit(i);
+        let c = carry(i);
+        let window = if i < n_unsigned_windows {
+            window_expr(
+                i,
+                window_size as usize,
+                input_limb_bitwidth,
+                n_input_limbs,
+                &input_tok,
+                &output_type,
+            )
+        } else {
+            quote! { 0 }
+        };
+        let carry_in = if i == 0 {
+            quote! {}
+        } else {
+            let prev = carry(i - 1);
+            quote! { + #prev }
+        };
+        body.append_all(quote! {
+            let #d = (#window) #carry_in;
+            let #c = ((#half - #d) >> #shift) & 1;
+            let #d = #d - (#c << #window_size);
+        });
+    }
+    let mut result = quote! {};
+    result.append_separated((0..n_windows).rev().map(digit), quote! { , });
+
     let expanded = quote! {
         pub(crate) struct #name;
 
-        impl SplitImpl for #name {
+        impl SignedSplitImpl for #name {
             const WINDOW_SIZE: usize = (#window_size) as usize;
             const N_WINDOWS: usize = #n_windows;
             type Output = #output_type;
 
+            #[allow(clippy::identity_op, unused_variables)]
             fn split(#input_tok: &[#input_type; #input_size]) -> [#output_type; #n_windows] {
-                [ #body ]
+                #body
+                [ #result ]
             }
         }
     };
 
-    TokenStream::from(expanded)
+    Ok(expanded)
 }
@@ -0,0 +1,5 @@
+use msm_macro::define_msm_scalar_splitter;
+
+define_msm_scalar_splitter! { Split16: [f32; 8] -> [16u32] }
+
+fn main() {}
@@ -0,0 +1,5 @@
+use msm_macro::define_msm_scalar_splitter;
+
+define_msm_scalar_splitter! { Split15S: [u32; 8] -> [15i16 signed] }
+
+fn main() {}
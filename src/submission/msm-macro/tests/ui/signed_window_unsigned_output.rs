@@ -0,0 +1,5 @@
+use msm_macro::define_msm_scalar_splitter;
+
+define_msm_scalar_splitter! { Split16S: [u32; 8] -> [16u32 signed] }
+
+fn main() {}
@@ -0,0 +1,5 @@
+use msm_macro::define_msm_scalar_splitter;
+
+define_msm_scalar_splitter! { Split9: [u8; 32] -> [9] }
+
+fn main() {}
@@ -0,0 +1,5 @@
+use msm_macro::define_msm_scalar_splitter;
+
+define_msm_scalar_splitter! { Split0: [u32; 8] -> [0u32] }
+
+fn main() {}
@@ -0,0 +1,188 @@
+//! Benchmarks for each phase of the CPU pipeline on Edwards BLS12-377 points,
+//! over MSM sizes from 2^10 to 2^22 and every compiled window size.
+//!
+//! Every combination takes a while, so filter the benchmarks by name, e.g.:
+//!
+//! ```text
+//! cargo bench --bench phases -- 'split/w16|bucket_cpu/w16'
+//! ```
+
+use std::ops::RangeInclusive;
+use std::sync::OnceLock;
+
+use ark_ec::CurveGroup;
+use ark_ed_on_bls12_377::EdwardsProjective;
+use ark_std::UniformRand;
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use msm_wasm::native;
+use msm_wasm::test_data::{self, ScalarDistribution};
+use msm_wasm::WINDOW_SIZES;
+
+const POWERS: RangeInclusive<u32> = 10..=22;
+
+struct Inputs {
+    points: Vec<EdwardsProjective>,
+    points_flat: Vec<u32>,
+    scalars_flat: Vec<u32>,
+}
+
+impl Inputs {
+    fn points_flat(&self, n: usize) -> &[u32] {
+        &self.points_flat[..self.points_flat.len() / self.points.len() * n]
+    }
+}
+
+/// Inputs for the largest size, of which smaller sizes use a prefix. Only
+/// generated once a benchmark needs them, so that filtering stays fast.
+fn inputs() -> &'static Inputs {
+    static INPUTS: OnceLock<Inputs> = OnceLock::new();
+    INPUTS.get_or_init(|| {
+        let n = 1 << POWERS.end();
+        // Consecutive multiples of a random point are much faster to generate
+        // than independent random points, and cost the same to add.
+        let base = EdwardsProjective::rand(&mut ark_std::test_rng());
+        let mut points = Vec::with_capacity(n);
+        let mut point = base;
+        for _ in 0..n {
+            points.push(point);
+            point += base;
+        }
+        let points = EdwardsProjective::normalize_batch(&points)
+            .into_iter()
+            .map(EdwardsProjective::from)
+            .collect::<Vec<_>>();
+        let points_flat = native::points_to_flat(&points);
+        let scalars_flat = test_data::generate_scalars(n, ScalarDistribution::Uniform, 0);
+        Inputs {
+            points,
+            points_flat,
+            scalars_flat,
+        }
+    })
+}
+
+fn size_id(power: u32) -> BenchmarkId {
+    BenchmarkId::from_parameter(format!("2^{}", power))
+}
+
+fn bench_read_points(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_points");
+    group.sample_size(10);
+    for power in POWERS {
+        let n = 1 << power;
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_function(size_id(power), |b| {
+            let points_flat = inputs().points_flat(n);
+            b.iter(|| native::read_points(black_box(points_flat)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_split(c: &mut Criterion) {
+    for &window_size in WINDOW_SIZES {
+        let window_size = window_size as usize;
+        let mut group = c.benchmark_group(format!("split/w{}", window_size));
+        group.sample_size(10);
+        for power in POWERS {
+            let n = 1 << power;
+            group.throughput(Throughput::Elements(n as u64));
+            group.bench_function(size_id(power), |b| {
+                let scalars_flat = &inputs().scalars_flat[..8 * n];
+                b.iter(|| native::split(black_box(scalars_flat), window_size).unwrap())
+            });
+        }
+        group.finish();
+    }
+}
+
+/// Benchmarks bucketing the least significant window, which has uniformly
+/// distributed digits. A whole MSM buckets every window like this.
+fn bench_bucket_cpu(c: &mut Criterion) {
+    for &window_size in WINDOW_SIZES {
+        let window_size = window_size as usize;
+        let mut group = c.benchmark_group(format!("bucket_cpu/w{}", window_size));
+        group.sample_size(10);
+        for power in POWERS {
+            let n = 1 << power;
+            let mut digits = None;
+            group.throughput(Throughput::Elements(n as u64));
+            group.bench_function(size_id(power), |b| {
+                let inputs = inputs();
+                let digits = digits.get_or_insert_with(|| {
+                    let split = native::split(&inputs.scalars_flat[..8 * n], window_size).unwrap();
+                    split[split.len() - n..].to_vec()
+                });
+                let points = &inputs.points[..n];
+                b.iter(|| native::bucket_cpu(black_box(digits), points, 1 << window_size))
+            });
+        }
+        group.finish();
+    }
+}
+
+/// Benchmarks summing the `2^w` buckets of a window, which does not depend on
+/// the MSM size.
+fn bench_bucket_sum(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bucket_sum_cpu");
+    group.sample_size(10);
+    for &window_size in WINDOW_SIZES {
+        let n_buckets = 1 << window_size;
+        group.throughput(Throughput::Elements(n_buckets as u64));
+        group.bench_function(BenchmarkId::from_parameter(window_size), |b| {
+            let bucket = &inputs().points[..n_buckets];
+            b.iter_batched(
+                || bucket.to_vec(),
+                native::bucket_sum_cpu,
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("bucket_sum_par");
+    group.sample_size(10);
+    for &window_size in WINDOW_SIZES {
+        let n_buckets = 1 << window_size;
+        group.throughput(Throughput::Elements(n_buckets as u64));
+        group.bench_function(BenchmarkId::from_parameter(window_size), |b| {
+            let bucket = &inputs().points[..n_buckets];
+            b.iter(|| native::bucket_sum_par(black_box(bucket)))
+        });
+    }
+    group.finish();
+}
+
+/// Benchmarks combining the bucket sums of every window, which does not depend
+/// on the MSM size.
+fn bench_reduce_last(c: &mut Criterion) {
+    let mut group = c.benchmark_group("reduce_last");
+    for &window_size in WINDOW_SIZES {
+        let window_size = window_size as usize;
+        group.bench_function(BenchmarkId::from_parameter(window_size), |b| {
+            let inputs = inputs();
+            let n_windows = native::split(&inputs.scalars_flat[..8], window_size)
+                .unwrap()
+                .len();
+            let bucket_sums = &inputs.points[..n_windows];
+            b.iter_batched(
+                || bucket_sums.to_vec(),
+                |bucket_sums| native::reduce_last(bucket_sums, window_size),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_read_points,
+    bench_split,
+    bench_bucket_cpu,
+    bench_bucket_sum,
+    bench_reduce_last
+);
+criterion_main!(benches);
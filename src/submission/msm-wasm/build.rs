@@ -0,0 +1,40 @@
+//! Generates the scalar splitters and the list of window sizes from the
+//! enabled `window-N` features.
+
+use std::{env, fs, path::Path};
+
+fn main() {
+    let mut window_sizes = env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_WINDOW_")?.parse().ok())
+        .collect::<Vec<u32>>();
+    window_sizes.sort_unstable();
+    if window_sizes.is_empty() {
+        panic!("At least one `window-N` feature must be enabled");
+    }
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+
+    let mut splitters = String::new();
+    for w in &window_sizes {
+        splitters += &format!("define_msm_scalar_splitter! {{ Split{w}: [u32; 8] -> [{w}u32] }}\n");
+        splitters +=
+            &format!("define_msm_scalar_splitter! {{ Split{w}S: [u32; 8] -> [{w}i32 signed] }}\n");
+    }
+    fs::write(Path::new(&out_dir).join("splitters.rs"), splitters).unwrap();
+
+    let list = window_sizes
+        .iter()
+        .map(|w| w.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let window_sizes = format!(
+        "macro_rules! with_window_sizes {{\n    \
+         ($m:ident!($($args:tt)*)) => {{\n        \
+         $m!($($args)* {list});\n    \
+         }};\n\
+         }}\n"
+    );
+    fs::write(Path::new(&out_dir).join("window_sizes.rs"), window_sizes).unwrap();
+
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,221 @@
+//! Bucket accumulation in affine coordinates with batched inversions.
+//!
+//! Adding two affine points needs a field inversion, but with Montgomery's
+//! trick, `k` independent inversions cost one inversion plus `3(k - 1)`
+//! multiplications. Collecting many additions into distinct buckets and
+//! finishing them together therefore makes each addition cheaper than a
+//! projective one, especially for short Weierstrass curves.
+
+use std::collections::VecDeque;
+
+use ark_ec::{
+    short_weierstrass::{self, SWCurveConfig},
+    twisted_edwards::{self, TECurveConfig},
+    AffineRepr, CurveGroup,
+};
+use ark_ff::{batch_inversion, Field, One, Zero};
+
+use crate::BucketDigit;
+
+/// Number of independent additions that share one batch inversion.
+pub(crate) const BATCH_SIZE: usize = 256;
+/// Maximum number of points waiting for a busy bucket. Beyond this, points are
+/// added to their bucket in projective coordinates instead.
+pub(crate) const MAX_QUEUE_SIZE: usize = BATCH_SIZE;
+
+/// Trait for curves whose affine addition can be split into computing a
+/// denominator and finishing the addition once its inverse is known.
+pub trait BatchAddAffine: CurveGroup {
+    /// Returns the field element to invert in order to compute `a + b`. Must
+    /// be non-zero.
+    fn add_denominator(a: &Self::Affine, b: &Self::Affine) -> Self::BaseField;
+
+    /// Computes `a + b` given the inverse of `add_denominator(a, b)`.
+    fn add_with_inverse(a: &Self::Affine, b: &Self::Affine, inv: &Self::BaseField) -> Self::Affine;
+
+    fn neg_affine(a: &Self::Affine) -> Self::Affine;
+}
+
+// Twisted Edwards affine addition:
+//   x3 = (x1 y2 + y1 x2) / (1 + d x1 x2 y1 y2)
+//   y3 = (y1 y2 - a x1 x2) / (1 - d x1 x2 y1 y2)
+// Both denominators are inverted at once through their product 1 - t^2, where
+// t = d x1 x2 y1 y2. The formulas are complete, so there are no special cases.
+impl<P: TECurveConfig> BatchAddAffine for twisted_edwards::Projective<P> {
+    fn add_denominator(
+        a: &twisted_edwards::Affine<P>,
+        b: &twisted_edwards::Affine<P>,
+    ) -> P::BaseField {
+        let t = P::COEFF_D * a.x * b.x * a.y * b.y;
+        P::BaseField::one() - t.square()
+    }
+
+    fn add_with_inverse(
+        a: &twisted_edwards::Affine<P>,
+        b: &twisted_edwards::Affine<P>,
+        inv: &P::BaseField,
+    ) -> twisted_edwards::Affine<P> {
+        let x1x2 = a.x * b.x;
+        let y1y2 = a.y * b.y;
+        let t = P::COEFF_D * x1x2 * y1y2;
+        let one = P::BaseField::one();
+        let x = (a.x * b.y + a.y * b.x) * (one - t) * inv;
+        let y = (y1y2 - P::mul_by_a(x1x2)) * (one + t) * inv;
+        twisted_edwards::Affine::new_unchecked(x, y)
+    }
+
+    fn neg_affine(a: &twisted_edwards::Affine<P>) -> twisted_edwards::Affine<P> {
+        -*a
+    }
+}
+
+// Short Weierstrass affine addition:
+//   lambda = (y2 - y1) / (x2 - x1), or (3 x1^2 + a) / (2 y1) when doubling,
+//   x3 = lambda^2 - x1 - x2,
+//   y3 = lambda (x1 - x3) - y1.
+// Cases that do not need an inversion (an operand or the result being the
+// point at infinity) use a dummy denominator of 1.
+impl<P: SWCurveConfig> BatchAddAffine for short_weierstrass::Projective<P> {
+    fn add_denominator(
+        a: &short_weierstrass::Affine<P>,
+        b: &short_weierstrass::Affine<P>,
+    ) -> P::BaseField {
+        if a.infinity || b.infinity {
+            P::BaseField::one()
+        } else if a.x != b.x {
+            b.x - a.x
+        } else if a.y == b.y && !a.y.is_zero() {
+            a.y.double()
+        } else {
+            P::BaseField::one()
+        }
+    }
+
+    fn add_with_inverse(
+        a: &short_weierstrass::Affine<P>,
+        b: &short_weierstrass::Affine<P>,
+        inv: &P::BaseField,
+    ) -> short_weierstrass::Affine<P> {
+        if a.infinity {
+            return *b;
+        }
+        if b.infinity {
+            return *a;
+        }
+        let lambda = if a.x != b.x {
+            (b.y - a.y) * inv
+        } else if a.y == b.y && !a.y.is_zero() {
+            (a.x.square() * P::BaseField::from(3u8) + P::COEFF_A) * inv
+        } else {
+            return short_weierstrass::Affine::identity();
+        };
+        let x = lambda.square() - a.x - b.x;
+        let y = lambda * (a.x - x) - a.y;
+        short_weierstrass::Affine::new_unchecked(x, y)
+    }
+
+    fn neg_affine(a: &short_weierstrass::Affine<P>) -> short_weierstrass::Affine<P> {
+        -*a
+    }
+}
+
+/// State of the bucket accumulation for a single window.
+struct BatchState<G: BatchAddAffine> {
+    buckets: Vec<G::Affine>,
+    /// Whether a bucket already has an addition scheduled in the current
+    /// batch. A bucket can only take part in one addition per batch.
+    busy: Vec<bool>,
+    /// Additions scheduled in the current batch.
+    pending: Vec<(usize, G::Affine)>,
+    /// Points that hit a busy bucket, to be retried after the next batch.
+    queue: VecDeque<(usize, G::Affine)>,
+    /// Points that did not fit into the queue, accumulated projectively.
+    overflow: Vec<G>,
+    denominators: Vec<G::BaseField>,
+}
+
+impl<G: BatchAddAffine> BatchState<G> {
+    fn new(n_buckets: usize) -> Self {
+        Self {
+            buckets: vec![G::Affine::zero(); n_buckets],
+            busy: vec![false; n_buckets],
+            pending: Vec::with_capacity(BATCH_SIZE),
+            queue: VecDeque::with_capacity(MAX_QUEUE_SIZE),
+            overflow: vec![G::zero(); n_buckets],
+            denominators: Vec::with_capacity(BATCH_SIZE),
+        }
+    }
+
+    fn schedule(&mut self, bucket_id: usize, point: G::Affine) {
+        if self.busy[bucket_id] {
+            if self.queue.len() < MAX_QUEUE_SIZE {
+                self.queue.push_back((bucket_id, point));
+            } else {
+                self.overflow[bucket_id] += point;
+            }
+        } else if self.buckets[bucket_id].is_zero() {
+            self.buckets[bucket_id] = point;
+        } else {
+            self.busy[bucket_id] = true;
+            self.pending.push((bucket_id, point));
+        }
+    }
+
+    /// Finishes all pending additions with one batch inversion, then retries
+    /// the queued points.
+    fn flush(&mut self) {
+        self.denominators.clear();
+        self.denominators.extend(
+            self.pending
+                .iter()
+                .map(|(bucket_id, point)| G::add_denominator(&self.buckets[*bucket_id], point)),
+        );
+        batch_inversion(&mut self.denominators);
+        for ((bucket_id, point), inv) in self.pending.drain(..).zip(self.denominators.iter()) {
+            self.buckets[bucket_id] = G::add_with_inverse(&self.buckets[bucket_id], &point, inv);
+            self.busy[bucket_id] = false;
+        }
+        for _ in 0..self.queue.len() {
+            let (bucket_id, point) = self.queue.pop_front().unwrap();
+            self.schedule(bucket_id, point);
+        }
+    }
+
+    fn finish(mut self) -> Vec<G> {
+        while !self.pending.is_empty() || !self.queue.is_empty() {
+            self.flush();
+        }
+        for (sum, bucket) in self.overflow.iter_mut().zip(self.buckets) {
+            *sum += bucket;
+        }
+        self.overflow
+    }
+}
+
+/// Same as `bucket_cpu`, but accumulates the buckets in affine coordinates
+/// with batched inversions. `points` must be in affine form already.
+pub(crate) fn bucket_batch_affine<G, D>(
+    scalars: &[D],
+    points: &[G::Affine],
+    n_buckets: usize,
+) -> Vec<G>
+where
+    G: BatchAddAffine,
+    D: BucketDigit,
+{
+    let mut state = BatchState::<G>::new(n_buckets);
+    for (scalar, point) in scalars.iter().zip(points.iter()) {
+        let (bucket_id, negate) = scalar.bucket();
+        if bucket_id == 0 {
+            continue;
+        }
+        // Digits from the splitters are always smaller than `n_buckets`.
+        debug_assert!(bucket_id < n_buckets);
+        let point = if negate { G::neg_affine(point) } else { *point };
+        state.schedule(bucket_id, point);
+        if state.pending.len() >= BATCH_SIZE {
+            state.flush();
+        }
+    }
+    state.finish()
+}
@@ -0,0 +1,124 @@
+//! Generates deterministic MSM workloads in the format of `public/test-data`.
+//!
+//! ```text
+//! gen-test-data --power 22 [--seed 0] [--distributions uniform,sparse,small16,repeated,max] [--out test-data]
+//! ```
+//!
+//! Writes `2^power` points, one scalar file per distribution and the expected
+//! result of each MSM. See `msm_wasm::test_data` for the file layout.
+
+use std::path::PathBuf;
+use std::time::Instant;
+use std::{env, fs, process};
+
+use msm_wasm::test_data::{self, ScalarDistribution};
+
+struct Args {
+    power: u32,
+    seed: u64,
+    distributions: Vec<ScalarDistribution>,
+    out: PathBuf,
+}
+
+const USAGE: &str =
+    "usage: gen-test-data --power K [--seed S] [--distributions D1,D2,...] [--out DIR]
+  --power K          generate 2^K points and scalars
+  --seed S           RNG seed (default 0)
+  --distributions    comma-separated list of uniform, sparse, smallN, repeated and max
+                     (default uniform)
+  --out DIR          output directory (default test-data)";
+
+fn parse_args() -> Result<Args, String> {
+    let mut power = None;
+    let mut seed = 0;
+    let mut distributions = vec![ScalarDistribution::Uniform];
+    let mut out = PathBuf::from("test-data");
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--power" => {
+                let value = value()?;
+                power = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid power `{}`", value))?,
+                );
+            }
+            "--seed" => {
+                let value = value()?;
+                seed = value
+                    .parse()
+                    .map_err(|_| format!("invalid seed `{}`", value))?;
+            }
+            "--distributions" => {
+                distributions = value()?
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()?;
+            }
+            "--out" => out = PathBuf::from(value()?),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ => return Err(format!("unknown argument `{}`", arg)),
+        }
+    }
+    let power = power.ok_or("missing --power")?;
+    if power >= usize::BITS {
+        return Err(format!("power {} is too large", power));
+    }
+    Ok(Args {
+        power,
+        seed,
+        distributions,
+        out,
+    })
+}
+
+fn run(args: Args) -> std::io::Result<()> {
+    for dir in ["points", "scalars", "expected"] {
+        fs::create_dir_all(args.out.join(dir))?;
+    }
+    let n = 1usize << args.power;
+
+    let start = Instant::now();
+    let points = test_data::generate_points(n, args.seed);
+    let path = test_data::points_path(&args.out, args.power);
+    test_data::save_points(&path, &points)?;
+    println!("{} ({:.1?})", path.display(), start.elapsed());
+
+    for distribution in args.distributions {
+        let start = Instant::now();
+        let scalars_flat = test_data::generate_scalars(n, distribution, args.seed);
+        let scalars_path =
+            test_data::distribution_scalars_path(&args.out, args.power, distribution);
+        test_data::save_scalars(&scalars_path, &scalars_flat)?;
+        let expected = test_data::compute_expected(&points, &scalars_flat);
+        let expected_path = test_data::expected_path(&args.out, args.power, distribution);
+        test_data::save_expected(&expected_path, &expected)?;
+        println!(
+            "{}, {} ({:.1?})",
+            scalars_path.display(),
+            expected_path.display(),
+            start.elapsed()
+        );
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, USAGE);
+        process::exit(2);
+    });
+    if let Err(e) = run(args) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,283 @@
+//! Runs the CPU MSM pipeline natively on a workload from files, for profiling
+//! with native tools.
+//!
+//! ```text
+//! msm-cli (--power K [--dir DIR] [--distribution D] | --points FILE --scalars FILE)
+//!         [--window-size W] [--threads T] [--signed] [--strategy S] [--repeat N]
+//!         [--expected FILE | --check]
+//! ```
+//!
+//! Prints the affine result and the time spent in each phase of every run, and
+//! exits with status 1 if the result does not match the expected one.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use std::{env, process};
+
+use ark_ed_on_bls12_377::EdwardsAffine;
+use ark_ff::PrimeField;
+use msm_wasm::native::{self, MsmOptions};
+use msm_wasm::test_data::{self, ScalarDistribution};
+use msm_wasm::{choose_window_size, BucketStrategy, Span};
+
+enum Input {
+    /// A workload in `test_data` layout.
+    Power {
+        power: u32,
+        dir: PathBuf,
+        distribution: Option<ScalarDistribution>,
+    },
+    Files {
+        points: PathBuf,
+        scalars: PathBuf,
+    },
+}
+
+enum Check {
+    None,
+    /// Compare against an expected result file.
+    File(PathBuf),
+    /// Compare against the known result of a standard workload, or else
+    /// against arkworks.
+    Reference,
+}
+
+struct Args {
+    input: Input,
+    window_size: Option<usize>,
+    threads: Option<usize>,
+    signed: bool,
+    strategy: BucketStrategy,
+    repeat: usize,
+    check: Check,
+}
+
+const USAGE: &str =
+    "usage: msm-cli (--power K [--dir DIR] [--distribution D] | --points FILE --scalars FILE) [OPTIONS]
+  --power K          run the 2^K workload in test-data layout
+  --dir DIR          test-data directory (default public/test-data)
+  --distribution D   scalar distribution written by gen-test-data (default: the
+                     original scalars)
+  --points FILE      points file, one {\"x\",\"y\",\"t\",\"z\"} object per line
+  --scalars FILE     scalars file, one decimal scalar per line
+  --window-size W    window size (default: chosen by choose_window_size)
+  --threads T        number of threads (default: all cores)
+  --signed           use signed digits
+  --strategy S       projective or batch-affine (default projective)
+  --repeat N         run the MSM N times (default 1)
+  --expected FILE    check the result against an expected result file
+  --check            check the result against the known result, or arkworks";
+
+fn parse_args() -> Result<Args, String> {
+    let mut power = None;
+    let mut dir = None;
+    let mut distribution = None;
+    let mut points = None;
+    let mut scalars = None;
+    let mut window_size = None;
+    let mut threads = None;
+    let mut signed = false;
+    let mut strategy = BucketStrategy::Projective;
+    let mut repeat = 1;
+    let mut check = Check::None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--power" => power = Some(parse_number(&value()?, "power")?),
+            "--dir" => dir = Some(PathBuf::from(value()?)),
+            "--distribution" => distribution = Some(value()?.parse()?),
+            "--points" => points = Some(PathBuf::from(value()?)),
+            "--scalars" => scalars = Some(PathBuf::from(value()?)),
+            "--window-size" => window_size = Some(parse_number(&value()?, "window size")?),
+            "--threads" => threads = Some(parse_number(&value()?, "thread count")?),
+            "--signed" => signed = true,
+            "--strategy" => {
+                strategy = match value()?.as_str() {
+                    "projective" => BucketStrategy::Projective,
+                    "batch-affine" => BucketStrategy::BatchAffine,
+                    other => return Err(format!("unknown strategy `{}`", other)),
+                }
+            }
+            "--repeat" => repeat = parse_number(&value()?, "repeat count")?,
+            "--expected" => check = Check::File(PathBuf::from(value()?)),
+            "--check" => check = Check::Reference,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ => return Err(format!("unknown argument `{}`", arg)),
+        }
+    }
+    let input = match (power, points, scalars) {
+        (Some(power), None, None) => Input::Power {
+            power,
+            dir: dir.unwrap_or_else(test_data::default_dir),
+            distribution,
+        },
+        (None, Some(points), Some(scalars)) if dir.is_none() && distribution.is_none() => {
+            Input::Files { points, scalars }
+        }
+        _ => return Err(
+            "expected either --power, or --points and --scalars without --dir or --distribution"
+                .into(),
+        ),
+    };
+    if threads == Some(0) {
+        return Err("thread count must be positive".into());
+    }
+    if repeat == 0 {
+        return Err("repeat count must be positive".into());
+    }
+    Ok(Args {
+        input,
+        window_size,
+        threads,
+        signed,
+        strategy,
+        repeat,
+        check,
+    })
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid {} `{}`", name, value))
+}
+
+/// Formats the durations of the top-level spans and their total. Children
+/// are summarized by name, as there is one per window for some phases.
+fn format_spans(spans: &[Span]) -> String {
+    let total: f64 = spans.iter().map(|span| span.duration_ms).sum();
+    let mut line = String::new();
+    for span in spans {
+        line += &format!("{} {}", span.name, format_ms(span.duration_ms));
+        let mut names = Vec::new();
+        for child in &span.children {
+            if !names.contains(&child.name) {
+                names.push(child.name);
+            }
+        }
+        for name in names {
+            let children = span.children.iter().filter(|child| child.name == name);
+            let (count, sum) = children.fold((0, 0.0), |(count, sum), child| {
+                (count + 1, sum + child.duration_ms)
+            });
+            line += &format!(" ({} x{} {})", name, count, format_ms(sum));
+        }
+        line += ", ";
+    }
+    line + &format!("total {}", format_ms(total))
+}
+
+fn format_ms(ms: f64) -> String {
+    format!("{:.1?}", Duration::from_secs_f64(ms / 1000.0))
+}
+
+fn print_point(point: &EdwardsAffine) {
+    println!("x = {}", point.x.into_bigint());
+    println!("y = {}", point.y.into_bigint());
+}
+
+fn run(args: Args) -> Result<bool, String> {
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|e| e.to_string())?;
+    }
+    let (points_path, scalars_path) = match &args.input {
+        Input::Power {
+            power,
+            dir,
+            distribution: None,
+        } => (
+            test_data::points_path(dir, *power),
+            test_data::scalars_path(dir, *power),
+        ),
+        Input::Power {
+            power,
+            dir,
+            distribution: Some(distribution),
+        } => (
+            test_data::points_path(dir, *power),
+            test_data::distribution_scalars_path(dir, *power, *distribution),
+        ),
+        Input::Files { points, scalars } => (points.clone(), scalars.clone()),
+    };
+
+    let start = Instant::now();
+    let points = test_data::load_points(&points_path).map_err(|e| e.to_string())?;
+    let scalars_flat = test_data::load_scalars(&scalars_path).map_err(|e| e.to_string())?;
+    let points_flat = native::points_to_flat(&points);
+    println!("loaded {} points ({:.1?})", points.len(), start.elapsed());
+
+    let threads = rayon::current_num_threads();
+    let options = MsmOptions {
+        window_size: args
+            .window_size
+            .unwrap_or_else(|| choose_window_size(points.len(), threads) as usize),
+        signed: args.signed,
+        strategy: args.strategy,
+    };
+    println!(
+        "window size {}, {} threads, {}{:?}",
+        options.window_size,
+        threads,
+        if options.signed { "signed, " } else { "" },
+        options.strategy
+    );
+
+    let mut result = None;
+    for i in 0..args.repeat {
+        let (run_result, spans) =
+            native::msm_end_to_end_timed(&scalars_flat, &points_flat, options)
+                .map_err(|e| e.to_string())?;
+        println!("run {}: {}", i + 1, format_spans(&spans));
+        result = Some(run_result);
+    }
+    let result = result.unwrap();
+    print_point(&result);
+
+    let expected: EdwardsAffine = match args.check {
+        Check::None => return Ok(true),
+        Check::File(path) => test_data::load_expected(&path).map_err(|e| e.to_string())?,
+        Check::Reference => match args.input {
+            Input::Power {
+                power,
+                distribution: None,
+                ..
+            } => test_data::expected_result(power),
+            _ => None,
+        }
+        .unwrap_or_else(|| test_data::compute_expected(&points, &scalars_flat)),
+    };
+    if result == expected {
+        println!("result matches");
+        Ok(true)
+    } else {
+        println!("result does not match, expected");
+        print_point(&expected);
+        Ok(false)
+    }
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, USAGE);
+        process::exit(2);
+    });
+    match run(args) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
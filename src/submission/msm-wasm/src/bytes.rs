@@ -1,107 +1,315 @@
 #![allow(dead_code)]
 //! Handles serialization of EC points to and from bytes.
 
-use ark_ed_on_bls12_377::{EdwardsProjective, Fq};
-use ark_ff::{BigInt, PrimeField};
-
-// 4 components per point, 8 u32s per component
-pub const N_U32S_PER_POINT: usize = 4 * 8;
-pub const N_BYTES_PER_POINT: usize = N_U32S_PER_POINT * 4;
-
-pub fn read_fq(buf: &[u32]) -> Fq {
-    debug_assert_eq!(buf.len(), 8);
-    let bigint = BigInt([
-        ((buf[6] as u64) << 32) + buf[7] as u64,
-        ((buf[4] as u64) << 32) + buf[5] as u64,
-        ((buf[2] as u64) << 32) + buf[3] as u64,
-        ((buf[0] as u64) << 32) + buf[1] as u64,
-    ]);
-    Fq::from_bigint(bigint).unwrap()
-}
-
-pub fn read_fq_le(buf: &[u32]) -> Fq {
-    debug_assert_eq!(buf.len(), 8);
-    let bigint = BigInt([
-        ((buf[1] as u64) << 32) + buf[0] as u64,
-        ((buf[3] as u64) << 32) + buf[2] as u64,
-        ((buf[5] as u64) << 32) + buf[4] as u64,
-        ((buf[7] as u64) << 32) + buf[6] as u64,
-    ]);
-    Fq::from_bigint(bigint).unwrap()
-}
-
-pub fn write_fq(buf: &mut [u32], fq: &Fq) {
-    debug_assert_eq!(buf.len(), 8);
+use ark_ec::{
+    short_weierstrass::{self, SWCurveConfig},
+    twisted_edwards::{self, TECurveConfig},
+    CurveGroup,
+};
+use ark_ff::{BigInteger, Field, Fp, FpConfig, PrimeField, QuadExtConfig, QuadExtField, Zero};
+
+use crate::error::MsmError;
+
+/// Returns `None` if the value is not smaller than the field modulus.
+pub fn read_fq<F: PrimeField>(buf: &[u32]) -> Option<F> {
+    debug_assert_eq!(buf.len(), F::BigInt::NUM_LIMBS * 2);
+    let mut bigint = F::BigInt::default();
+    for (limb, chunk) in bigint.as_mut().iter_mut().zip(buf.chunks_exact(2).rev()) {
+        *limb = ((chunk[0] as u64) << 32) + chunk[1] as u64;
+    }
+    F::from_bigint(bigint)
+}
+
+pub fn read_fq_le<F: PrimeField>(buf: &[u32]) -> Option<F> {
+    debug_assert_eq!(buf.len(), F::BigInt::NUM_LIMBS * 2);
+    let mut bigint = F::BigInt::default();
+    for (limb, chunk) in bigint.as_mut().iter_mut().zip(buf.chunks_exact(2)) {
+        *limb = ((chunk[1] as u64) << 32) + chunk[0] as u64;
+    }
+    F::from_bigint(bigint)
+}
+
+pub fn write_fq<F: PrimeField>(buf: &mut [u32], fq: &F) {
+    debug_assert_eq!(buf.len(), F::BigInt::NUM_LIMBS * 2);
     let bigint = fq.into_bigint();
-    buf[7] = (bigint.0[0] & 0xffffffff) as u32;
-    buf[6] = (bigint.0[0] >> 32) as u32;
-    buf[5] = (bigint.0[1] & 0xffffffff) as u32;
-    buf[4] = (bigint.0[1] >> 32) as u32;
-    buf[3] = (bigint.0[2] & 0xffffffff) as u32;
-    buf[2] = (bigint.0[2] >> 32) as u32;
-    buf[1] = (bigint.0[3] & 0xffffffff) as u32;
-    buf[0] = (bigint.0[3] >> 32) as u32;
-}
-
-pub fn write_fq_le(buf: &mut [u32], fq: &Fq) {
-    debug_assert_eq!(buf.len(), 8);
+    for (limb, chunk) in bigint.as_ref().iter().zip(buf.chunks_exact_mut(2).rev()) {
+        chunk[1] = (limb & 0xffffffff) as u32;
+        chunk[0] = (limb >> 32) as u32;
+    }
+}
+
+pub fn write_fq_le<F: PrimeField>(buf: &mut [u32], fq: &F) {
+    debug_assert_eq!(buf.len(), F::BigInt::NUM_LIMBS * 2);
     let bigint = fq.into_bigint();
-    buf[0] = (bigint.0[0] & 0xffffffff) as u32;
-    buf[1] = (bigint.0[0] >> 32) as u32;
-    buf[2] = (bigint.0[1] & 0xffffffff) as u32;
-    buf[3] = (bigint.0[1] >> 32) as u32;
-    buf[4] = (bigint.0[2] & 0xffffffff) as u32;
-    buf[5] = (bigint.0[2] >> 32) as u32;
-    buf[6] = (bigint.0[3] & 0xffffffff) as u32;
-    buf[7] = (bigint.0[3] >> 32) as u32;
-}
-
-pub fn read_points(points_flat: &[u32]) -> Vec<EdwardsProjective> {
-    let n_points = points_flat.len() / N_U32S_PER_POINT;
-    let mut points = Vec::with_capacity(n_points);
-    for i in 0..n_points {
-        let x = read_fq(&points_flat[32 * i..32 * i + 8]);
-        let y = read_fq(&points_flat[32 * i + 8..32 * i + 16]);
-        let t = read_fq(&points_flat[32 * i + 16..32 * i + 24]);
-        let z = read_fq(&points_flat[32 * i + 24..32 * i + 32]);
-        let point = EdwardsProjective::new_unchecked(x, y, t, z);
-        points.push(point);
-    }
-    points
-}
-
-pub fn read_points_le(points_flat: &[u32]) -> Vec<EdwardsProjective> {
-    let n_points = points_flat.len() / N_U32S_PER_POINT;
-    let mut points = Vec::with_capacity(n_points);
-    for i in 0..n_points {
-        let x = read_fq_le(&points_flat[32 * i..32 * i + 8]);
-        let y = read_fq_le(&points_flat[32 * i + 8..32 * i + 16]);
-        let t = read_fq_le(&points_flat[32 * i + 16..32 * i + 24]);
-        let z = read_fq_le(&points_flat[32 * i + 24..32 * i + 32]);
-        let point = EdwardsProjective::new_unchecked(x, y, t, z);
-        points.push(point);
-    }
-    points
-}
-
-pub fn write_points(points: &[EdwardsProjective]) -> Vec<u32> {
-    let mut points_flat = vec![0u32; N_U32S_PER_POINT * points.len()];
-    for (i, point) in points.iter().enumerate() {
-        write_fq(&mut points_flat[32 * i..32 * i + 8], &point.x);
-        write_fq(&mut points_flat[32 * i + 8..32 * i + 16], &point.y);
-        write_fq(&mut points_flat[32 * i + 16..32 * i + 24], &point.t);
-        write_fq(&mut points_flat[32 * i + 24..32 * i + 32], &point.z);
+    for (limb, chunk) in bigint.as_ref().iter().zip(buf.chunks_exact_mut(2)) {
+        chunk[0] = (limb & 0xffffffff) as u32;
+        chunk[1] = (limb >> 32) as u32;
+    }
+}
+
+/// Trait for field elements that can be (de)serialized to and from flat `u32`
+/// buffers.
+pub trait FieldEncoding: Field {
+    /// Number of u32s per field element.
+    const N_U32S: usize;
+
+    /// Returns `None` if the buffer does not hold a canonical encoding.
+    fn read(buf: &[u32]) -> Option<Self>;
+    fn read_le(buf: &[u32]) -> Option<Self>;
+    fn write(&self, buf: &mut [u32]);
+    fn write_le(&self, buf: &mut [u32]);
+}
+
+// Prime field elements are stored as big integers in their canonical
+// (non-Montgomery) form, with either big-endian or little-endian u32 limbs.
+impl<P: FpConfig<N>, const N: usize> FieldEncoding for Fp<P, N> {
+    const N_U32S: usize = 2 * N;
+
+    fn read(buf: &[u32]) -> Option<Self> {
+        read_fq(buf)
+    }
+
+    fn read_le(buf: &[u32]) -> Option<Self> {
+        read_fq_le(buf)
+    }
+
+    fn write(&self, buf: &mut [u32]) {
+        write_fq(buf, self)
+    }
+
+    fn write_le(&self, buf: &mut [u32]) {
+        write_fq_le(buf, self)
+    }
+}
+
+// Quadratic extension field elements (e.g., Fq2) are stored as c0 followed by
+// c1, each in the base field's format.
+impl<P: QuadExtConfig> FieldEncoding for QuadExtField<P>
+where
+    P::BaseField: FieldEncoding,
+{
+    const N_U32S: usize = 2 * P::BaseField::N_U32S;
+
+    fn read(buf: &[u32]) -> Option<Self> {
+        let n = P::BaseField::N_U32S;
+        Some(Self::new(
+            P::BaseField::read(&buf[0..n])?,
+            P::BaseField::read(&buf[n..2 * n])?,
+        ))
+    }
+
+    fn read_le(buf: &[u32]) -> Option<Self> {
+        let n = P::BaseField::N_U32S;
+        Some(Self::new(
+            P::BaseField::read_le(&buf[0..n])?,
+            P::BaseField::read_le(&buf[n..2 * n])?,
+        ))
+    }
+
+    fn write(&self, buf: &mut [u32]) {
+        let n = P::BaseField::N_U32S;
+        self.c0.write(&mut buf[0..n]);
+        self.c1.write(&mut buf[n..2 * n]);
+    }
+
+    fn write_le(&self, buf: &mut [u32]) {
+        let n = P::BaseField::N_U32S;
+        self.c0.write_le(&mut buf[0..n]);
+        self.c1.write_le(&mut buf[n..2 * n]);
+    }
+}
+
+/// Trait for curve points that can be (de)serialized to and from flat `u32`
+/// buffers. Input points use a curve-specific representation (see the impls
+/// below), while results returned to JS are always stored in affine form.
+pub trait PointEncoding: CurveGroup {
+    /// Number of u32s per input point.
+    const N_U32S_PER_POINT: usize;
+    /// Number of u32s per point in affine form.
+    const N_U32S_PER_AFFINE: usize;
+
+    /// Returns `None` if a coordinate is not a canonical field element.
+    fn read_point(buf: &[u32]) -> Option<Self>;
+    fn read_point_le(buf: &[u32]) -> Option<Self>;
+    fn write_point(&self, buf: &mut [u32]);
+    fn write_point_le(&self, buf: &mut [u32]);
+    fn read_affine(buf: &[u32]) -> Option<Self::Affine>;
+    fn write_affine(affine: &Self::Affine, buf: &mut [u32]);
+}
+
+// Twisted Edwards points are stored as (x, y, t, z) in extended coordinates,
+// and as (x, y) in affine form.
+impl<P: TECurveConfig> PointEncoding for twisted_edwards::Projective<P>
+where
+    P::BaseField: FieldEncoding,
+{
+    const N_U32S_PER_POINT: usize = 4 * P::BaseField::N_U32S;
+    const N_U32S_PER_AFFINE: usize = 2 * P::BaseField::N_U32S;
+
+    fn read_point(buf: &[u32]) -> Option<Self> {
+        let n = P::BaseField::N_U32S;
+        let x = P::BaseField::read(&buf[0..n])?;
+        let y = P::BaseField::read(&buf[n..2 * n])?;
+        let t = P::BaseField::read(&buf[2 * n..3 * n])?;
+        let z = P::BaseField::read(&buf[3 * n..4 * n])?;
+        Some(Self::new_unchecked(x, y, t, z))
+    }
+
+    fn read_point_le(buf: &[u32]) -> Option<Self> {
+        let n = P::BaseField::N_U32S;
+        let x = P::BaseField::read_le(&buf[0..n])?;
+        let y = P::BaseField::read_le(&buf[n..2 * n])?;
+        let t = P::BaseField::read_le(&buf[2 * n..3 * n])?;
+        let z = P::BaseField::read_le(&buf[3 * n..4 * n])?;
+        Some(Self::new_unchecked(x, y, t, z))
+    }
+
+    fn write_point(&self, buf: &mut [u32]) {
+        let n = P::BaseField::N_U32S;
+        self.x.write(&mut buf[0..n]);
+        self.y.write(&mut buf[n..2 * n]);
+        self.t.write(&mut buf[2 * n..3 * n]);
+        self.z.write(&mut buf[3 * n..4 * n]);
+    }
+
+    fn write_point_le(&self, buf: &mut [u32]) {
+        let n = P::BaseField::N_U32S;
+        self.x.write_le(&mut buf[0..n]);
+        self.y.write_le(&mut buf[n..2 * n]);
+        self.t.write_le(&mut buf[2 * n..3 * n]);
+        self.z.write_le(&mut buf[3 * n..4 * n]);
+    }
+
+    fn read_affine(buf: &[u32]) -> Option<twisted_edwards::Affine<P>> {
+        let n = P::BaseField::N_U32S;
+        Some(twisted_edwards::Affine::new_unchecked(
+            P::BaseField::read(&buf[0..n])?,
+            P::BaseField::read(&buf[n..2 * n])?,
+        ))
+    }
+
+    fn write_affine(affine: &twisted_edwards::Affine<P>, buf: &mut [u32]) {
+        let n = P::BaseField::N_U32S;
+        affine.x.write(&mut buf[0..n]);
+        affine.y.write(&mut buf[n..2 * n]);
+    }
+}
+
+// Short Weierstrass points are always stored as (x, y, infinity) in affine
+// coordinates, where `infinity` is a single u32 that is non-zero iff the point
+// is the point at infinity (in which case x and y are ignored).
+impl<P: SWCurveConfig> PointEncoding for short_weierstrass::Projective<P>
+where
+    P::BaseField: FieldEncoding,
+{
+    const N_U32S_PER_POINT: usize = Self::N_U32S_PER_AFFINE;
+    const N_U32S_PER_AFFINE: usize = 2 * P::BaseField::N_U32S + 1;
+
+    fn read_point(buf: &[u32]) -> Option<Self> {
+        Self::read_affine(buf).map(Into::into)
+    }
+
+    fn read_point_le(buf: &[u32]) -> Option<Self> {
+        let n = P::BaseField::N_U32S;
+        if buf[2 * n] != 0 {
+            return Some(Self::zero());
+        }
+        let x = P::BaseField::read_le(&buf[0..n])?;
+        let y = P::BaseField::read_le(&buf[n..2 * n])?;
+        Some(short_weierstrass::Affine::new_unchecked(x, y).into())
+    }
+
+    fn write_point(&self, buf: &mut [u32]) {
+        Self::write_affine(&self.into_affine(), buf);
+    }
+
+    fn write_point_le(&self, buf: &mut [u32]) {
+        let n = P::BaseField::N_U32S;
+        let affine = self.into_affine();
+        if affine.infinity {
+            buf.fill(0);
+            buf[2 * n] = 1;
+        } else {
+            affine.x.write_le(&mut buf[0..n]);
+            affine.y.write_le(&mut buf[n..2 * n]);
+            buf[2 * n] = 0;
+        }
+    }
+
+    fn read_affine(buf: &[u32]) -> Option<short_weierstrass::Affine<P>> {
+        let n = P::BaseField::N_U32S;
+        if buf[2 * n] != 0 {
+            return Some(short_weierstrass::Affine::identity());
+        }
+        let x = P::BaseField::read(&buf[0..n])?;
+        let y = P::BaseField::read(&buf[n..2 * n])?;
+        Some(short_weierstrass::Affine::new_unchecked(x, y))
+    }
+
+    fn write_affine(affine: &short_weierstrass::Affine<P>, buf: &mut [u32]) {
+        let n = P::BaseField::N_U32S;
+        if affine.infinity {
+            buf.fill(0);
+            buf[2 * n] = 1;
+        } else {
+            affine.x.write(&mut buf[0..n]);
+            affine.y.write(&mut buf[n..2 * n]);
+            buf[2 * n] = 0;
+        }
+    }
+}
+
+pub fn read_points<G: PointEncoding>(points_flat: &[u32]) -> Result<Vec<G>, MsmError> {
+    read_points_with(points_flat, G::read_point)
+}
+
+pub fn read_points_le<G: PointEncoding>(points_flat: &[u32]) -> Result<Vec<G>, MsmError> {
+    read_points_with(points_flat, G::read_point_le)
+}
+
+fn read_points_with<G: PointEncoding>(
+    points_flat: &[u32],
+    read_point: impl Fn(&[u32]) -> Option<G>,
+) -> Result<Vec<G>, MsmError> {
+    if points_flat.len() % G::N_U32S_PER_POINT != 0 {
+        return Err(MsmError::InvalidLength {
+            name: "points",
+            len: points_flat.len(),
+            element_len: G::N_U32S_PER_POINT,
+        });
     }
     points_flat
+        .chunks_exact(G::N_U32S_PER_POINT)
+        .enumerate()
+        .map(|(index, buf)| read_point(buf).ok_or(MsmError::NonCanonicalFieldElement { index }))
+        .collect()
 }
 
-pub fn write_points_le(points: &[EdwardsProjective]) -> Vec<u32> {
-    let mut points_flat = vec![0u32; N_U32S_PER_POINT * points.len()];
-    for (i, point) in points.iter().enumerate() {
-        write_fq_le(&mut points_flat[32 * i..32 * i + 8], &point.x);
-        write_fq_le(&mut points_flat[32 * i + 8..32 * i + 16], &point.y);
-        write_fq_le(&mut points_flat[32 * i + 16..32 * i + 24], &point.t);
-        write_fq_le(&mut points_flat[32 * i + 24..32 * i + 32], &point.z);
+pub fn write_points<G: PointEncoding>(points: &[G]) -> Vec<u32> {
+    let mut points_flat = vec![0u32; G::N_U32S_PER_POINT * points.len()];
+    for (point, buf) in points
+        .iter()
+        .zip(points_flat.chunks_exact_mut(G::N_U32S_PER_POINT))
+    {
+        point.write_point(buf);
     }
     points_flat
 }
+
+pub fn write_points_le<G: PointEncoding>(points: &[G]) -> Vec<u32> {
+    let mut points_flat = vec![0u32; G::N_U32S_PER_POINT * points.len()];
+    for (point, buf) in points
+        .iter()
+        .zip(points_flat.chunks_exact_mut(G::N_U32S_PER_POINT))
+    {
+        point.write_point_le(buf);
+    }
+    points_flat
+}
+
+/// Serializes a single point in affine form.
+pub fn write_affine<G: PointEncoding>(point: &G::Affine) -> Vec<u32> {
+    let mut result_buf = vec![0u32; G::N_U32S_PER_AFFINE];
+    G::write_affine(point, &mut result_buf);
+    result_buf
+}
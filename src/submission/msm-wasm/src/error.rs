@@ -0,0 +1,98 @@
+//! Errors returned by the MSM functions. Exports convert them into `JsError`s,
+//! which are thrown on the JS side.
+
+use std::fmt;
+
+use crate::validate::InvalidPointReason;
+use crate::WINDOW_SIZES;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MsmError {
+    /// A flat buffer does not hold a whole number of elements.
+    InvalidLength {
+        name: &'static str,
+        len: usize,
+        element_len: usize,
+    },
+    /// A flat buffer does not hold exactly one element.
+    UnexpectedLength {
+        name: &'static str,
+        len: usize,
+        expected_len: usize,
+    },
+    /// The number of scalars does not match the number of points.
+    LengthMismatch { n_scalars: usize, n_points: usize },
+    /// A coordinate of the point at `index` is not smaller than the field
+    /// modulus.
+    NonCanonicalFieldElement { index: usize },
+    /// The point at `index` failed validation.
+    InvalidPoint {
+        index: usize,
+        reason: InvalidPointReason,
+    },
+    /// There is no splitter for the window size.
+    UnsupportedWindowSize(u32),
+    /// The window size is outside the range of a runtime splitter or MSM.
+    UnsupportedRuntimeWindowSize { window_size: u32, max: u32 },
+    /// A fixed-base table is used with a window size other than the one it was
+    /// computed for.
+    WindowSizeMismatch { table: u32, requested: u32 },
+    /// There are no points, so the number of MSMs in a batch is unknown.
+    EmptyInput,
+}
+
+impl fmt::Display for MsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MsmError::InvalidLength {
+                name,
+                len,
+                element_len,
+            } => write!(
+                f,
+                "Invalid length of {}: {} is not a multiple of {}",
+                name, len, element_len
+            ),
+            MsmError::UnexpectedLength {
+                name,
+                len,
+                expected_len,
+            } => write!(
+                f,
+                "Invalid length of {}: expected {} but got {}",
+                name, expected_len, len
+            ),
+            MsmError::LengthMismatch {
+                n_scalars,
+                n_points,
+            } => write!(f, "Got {} scalars but {} points", n_scalars, n_points),
+            MsmError::NonCanonicalFieldElement { index } => write!(
+                f,
+                "Point {} has a coordinate that is not a canonical field element",
+                index
+            ),
+            MsmError::InvalidPoint { index, reason } => {
+                write!(f, "Point {} is invalid: {}", index, reason)
+            }
+            MsmError::UnsupportedWindowSize(window_size) => write!(
+                f,
+                "Unsupported window size: {}. Compiled window sizes are {:?}; others \
+                 need a `window-N` feature or the `_runtime` exports.",
+                window_size, WINDOW_SIZES
+            ),
+            MsmError::UnsupportedRuntimeWindowSize { window_size, max } => write!(
+                f,
+                "Unsupported window size: {}. Runtime window sizes must be between 1 and {}.",
+                window_size, max
+            ),
+            MsmError::WindowSizeMismatch { table, requested } => write!(
+                f,
+                "The table was computed for window size {} but window size {} was requested",
+                table, requested
+            ),
+            MsmError::EmptyInput => write!(f, "No points were given"),
+        }
+    }
+}
+
+impl std::error::Error for MsmError {}
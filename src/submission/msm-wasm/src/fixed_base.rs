@@ -0,0 +1,116 @@
+//! Fixed-base MSM with precomputed tables.
+//!
+//! When the same bases are used for many MSMs, we can precompute
+//! `2^(k*w) * P_i` for every window `k` once. The digits of all windows can
+//! then be added into a single set of buckets, which removes both the per-window
+//! bucket sums and the doubling chain of `reduce_last`.
+//!
+//! A table for every window costs `N_WINDOWS` points per base. To trade speed
+//! for memory, tables can be stored for every `stride`-th window only. Windows
+//! are then bucketed in `stride` groups (window `k` goes to group
+//! `k % stride`, using table `k / stride`), and the group sums are combined
+//! with a doubling chain of `(stride - 1) * w` doublings. `stride = 1` is the
+//! fastest; `stride = N_WINDOWS` stores no extra points and degenerates to the
+//! plain Pippenger algorithm.
+
+use rayon::prelude::*;
+
+use crate::batch_affine::bucket_batch_affine;
+use crate::error::MsmError;
+use crate::split::SignedSplitImpl;
+use crate::{
+    bucket_sum_par, bucket_window, check_scalars, n_window_parts, reduce_last, split_signed,
+    MsmCurve,
+};
+
+pub struct FixedBase<G: MsmCurve> {
+    window_size: usize,
+    stride: usize,
+    n_points: usize,
+    /// `tables[t * n_points + i] = 2^(t * stride * window_size) * P_i`.
+    tables: Vec<G::Affine>,
+}
+
+impl<G: MsmCurve> FixedBase<G> {
+    /// Precomputes the tables for `points`. `stride` is clamped to
+    /// `[1, N_WINDOWS]`.
+    pub fn new<Split: SignedSplitImpl>(points: &[G], stride: usize) -> Self {
+        let stride = stride.clamp(1, Split::N_WINDOWS);
+        let n_tables = Split::N_WINDOWS.div_ceil(stride);
+        let shift = stride * Split::WINDOW_SIZE;
+        let mut tables = Vec::with_capacity(n_tables * points.len());
+        let mut current = points.to_vec();
+        for t in 0..n_tables {
+            if t > 0 {
+                current.par_iter_mut().for_each(|point| {
+                    for _ in 0..shift {
+                        point.double_in_place();
+                    }
+                });
+            }
+            tables.extend(G::normalize_batch(&current));
+        }
+        Self {
+            window_size: Split::WINDOW_SIZE,
+            stride,
+            n_points: points.len(),
+            tables,
+        }
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Computes the MSM of the bases with `scalars_flat`. `Split` must have the
+    /// window size the tables were computed for.
+    pub fn msm<Split>(&self, scalars_flat: &[u32]) -> Result<Vec<u32>, MsmError>
+    where
+        Split: SignedSplitImpl,
+        Split::Output: Into<i32> + Copy,
+        [(); Split::N_WINDOWS]: Sized,
+    {
+        if Split::WINDOW_SIZE != self.window_size {
+            return Err(MsmError::WindowSizeMismatch {
+                table: self.window_size as u32,
+                requested: Split::WINDOW_SIZE as u32,
+            });
+        }
+        check_scalars(scalars_flat, self.n_points)?;
+        let n = self.n_points;
+        if n == 0 {
+            return Ok(reduce_last::<G>(Vec::new(), Split::WINDOW_SIZE));
+        }
+        let digits = split_signed::<Split>(scalars_flat);
+
+        let n_tables = self.tables.len() / n;
+        let n_buckets = (1 << (Split::WINDOW_SIZE - 1)) + 1;
+        let n_parts = n_window_parts(self.stride, n * n_tables, n_buckets);
+        // Groups are processed from the most significant one, which is the
+        // order `reduce_last` expects.
+        let group_sums = (0..self.stride)
+            .into_par_iter()
+            .rev()
+            .map(|group| {
+                let mut group_digits = vec![0i32; n * n_tables];
+                for t in 0..n_tables {
+                    let k = t * self.stride + group;
+                    if k < Split::N_WINDOWS {
+                        // `split_signed` orders windows from the most
+                        // significant one.
+                        let j = Split::N_WINDOWS - 1 - k;
+                        group_digits[t * n..(t + 1) * n]
+                            .copy_from_slice(&digits[j * n..(j + 1) * n]);
+                    }
+                }
+                bucket_sum_par(&bucket_window(
+                    &group_digits,
+                    &self.tables,
+                    n_parts,
+                    |digits, points| bucket_batch_affine::<G, i32>(digits, points, n_buckets),
+                ))
+            })
+            .collect::<Vec<_>>();
+        Ok(reduce_last(group_sums, Split::WINDOW_SIZE))
+    }
+}
@@ -2,50 +2,114 @@
 #![allow(incomplete_features)]
 #![feature(generic_const_exprs)]
 
+mod batch_affine;
 mod bytes;
+mod error;
+mod fixed_base;
+mod spans;
 mod split;
+mod straus;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod test_data;
 mod utils;
+mod validate;
+mod window;
 
 use std::convert::TryInto;
+use std::sync::OnceLock;
 
-use ark_ec::{CurveGroup, Group};
-use ark_ed_on_bls12_377::{EdwardsAffine, EdwardsProjective};
-use ark_ff::Zero;
+use ark_bls12_377::{G1Projective as Bls12_377G1Projective, G2Projective as Bls12_377G2Projective};
+#[cfg(feature = "bls12-381")]
+use ark_bls12_381::G1Projective as Bls12_381G1Projective;
+#[cfg(feature = "bn254")]
+use ark_bn254::G1Projective as Bn254G1Projective;
+use ark_ec::CurveGroup;
+use ark_ed_on_bls12_377::EdwardsProjective;
+use ark_ed_on_bls12_381_bandersnatch::EdwardsProjective as BandersnatchProjective;
+use ark_ed_on_bn254::EdwardsProjective as BabyJubjubProjective;
 use paste::paste;
-use rayon::iter::ParallelIterator;
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
 
-use crate::bytes::{read_fq, read_points, write_fq};
+use crate::batch_affine::{bucket_batch_affine, BatchAddAffine};
+use crate::bytes::{read_points, write_affine, PointEncoding};
+use crate::error::MsmError;
+use crate::fixed_base::FixedBase;
 #[allow(unused_imports)]
+use crate::spans::span;
 use crate::split::*;
+use crate::straus::{msm_straus, SMALL_MSM_THRESHOLD};
+use crate::validate::ValidatePoint;
 use wasm_bindgen::prelude::*;
 
+// Defines `with_window_sizes!`, which appends the window sizes enabled through
+// `window-N` features to the arguments of a macro call. See `build.rs`.
+include!(concat!(env!("OUT_DIR"), "/window_sizes.rs"));
+
 static INIT: std::sync::Once = std::sync::Once::new();
 
-fn bucket_cpu(
-    scalars: &[u32],
-    points: &[EdwardsProjective],
-    n_buckets: usize,
-) -> Vec<EdwardsProjective> {
-    let mut bucket = vec![EdwardsProjective::zero(); n_buckets];
+/// Curves that can go through the whole CPU MSM pipeline.
+trait MsmCurve: PointEncoding + BatchAddAffine + ValidatePoint {}
+
+impl<G: PointEncoding + BatchAddAffine + ValidatePoint> MsmCurve for G {}
+
+/// How points are accumulated into buckets.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BucketStrategy {
+    /// Add points one at a time in projective coordinates.
+    Projective = 0,
+    /// Add points in affine coordinates, sharing one inversion across a batch
+    /// of additions into distinct buckets. See `batch_affine.rs`.
+    BatchAffine = 1,
+}
+
+/// A window of a split scalar, which selects a bucket and, for signed digits,
+/// whether the point should be negated before being added to it.
+trait BucketDigit: Copy + Send + Sync {
+    fn bucket(self) -> (usize, bool);
+}
+
+impl BucketDigit for u32 {
+    #[inline(always)]
+    fn bucket(self) -> (usize, bool) {
+        (self as usize, false)
+    }
+}
+
+impl BucketDigit for i32 {
+    #[inline(always)]
+    fn bucket(self) -> (usize, bool) {
+        (self.unsigned_abs() as usize, self < 0)
+    }
+}
+
+fn bucket_cpu<G, D>(scalars: &[D], points: &[G], n_buckets: usize) -> Vec<G>
+where
+    G: CurveGroup,
+    D: BucketDigit,
+{
+    let mut bucket = vec![G::zero(); n_buckets];
     for (scalar, point) in scalars.iter().zip(points.iter()) {
-        let bucket_id = (*scalar) as usize;
+        let (bucket_id, negate) = scalar.bucket();
         if bucket_id == 0 {
             continue;
         }
-        assert!(bucket_id < n_buckets);
+        // Digits from the splitters are always smaller than `n_buckets`.
+        debug_assert!(bucket_id < n_buckets);
         let existing = &bucket[bucket_id];
-        bucket[bucket_id] = if existing.is_zero() {
-            *point
-        } else {
-            existing + point
+        bucket[bucket_id] = match (existing.is_zero(), negate) {
+            (true, false) => *point,
+            (true, true) => -*point,
+            (false, false) => *existing + point,
+            (false, true) => *existing - point,
         };
     }
     bucket
 }
 
-fn bucket_sum_cpu(bucket: Vec<EdwardsProjective>) -> EdwardsProjective {
-    let mut sum = EdwardsProjective::zero();
-    let mut carry = EdwardsProjective::zero();
+fn bucket_sum_cpu<G: CurveGroup>(bucket: Vec<G>) -> G {
+    let mut sum = G::zero();
+    let mut carry = G::zero();
     for i in (1..bucket.len()).rev() {
         if !bucket[i].is_zero() {
             carry += bucket[i];
@@ -55,16 +119,85 @@ fn bucket_sum_cpu(bucket: Vec<EdwardsProjective>) -> EdwardsProjective {
     sum
 }
 
+/// Buckets at or below this count are summed sequentially by
+/// `bucket_sum_tree`.
+const BUCKET_SUM_LEAF_SIZE: usize = 1 << 10;
+
+/// Parallel version of `bucket_sum_cpu`, using the same reduction as the
+/// `entry_inter_bucket.wgsl` kernel. Given the two halves A and B of a bucket
+/// array of length n, and
+///   A1 = sum_{i=0}^{n/2-1} i*A[i],  A2 = (n/2) * sum_{i=0}^{n/2-1} A[i],
+///   B1 = sum_{i=0}^{n/2-1} i*B[i],  B2 = (n/2) * sum_{i=0}^{n/2-1} B[i],
+/// the whole array has sum_{i=0}^{n-1} i*bucket[i] = A1 + B1 + B2 and
+/// n * sum_{i=0}^{n-1} bucket[i] = 2 * (A2 + B2).
+fn bucket_sum_par<G: CurveGroup>(bucket: &[G]) -> G {
+    bucket_sum_tree(bucket, bucket.len().next_power_of_two()).0
+}
+
+/// Returns `(sum_i i*bucket[i], size * sum_i bucket[i])`, where `size` is a
+/// power of two no less than `bucket.len()`. Buckets past the end of the slice
+/// are treated as zero.
+fn bucket_sum_tree<G: CurveGroup>(bucket: &[G], size: usize) -> (G, G) {
+    if size <= BUCKET_SUM_LEAF_SIZE {
+        let mut sum = G::zero();
+        let mut carry = G::zero();
+        for point in bucket.iter().skip(1).rev() {
+            carry += point;
+            sum += carry;
+        }
+        if let Some(first) = bucket.first() {
+            carry += first;
+        }
+        for _ in 0..size.trailing_zeros() {
+            carry.double_in_place();
+        }
+        return (sum, carry);
+    }
+    let half = size / 2;
+    let (lo, hi) = bucket.split_at(half.min(bucket.len()));
+    let ((a1, a2), (b1, b2)) =
+        rayon::join(|| bucket_sum_tree(lo, half), || bucket_sum_tree(hi, half));
+    (a1 + b1 + b2, (a2 + b2).double())
+}
+
+fn init() {
+    INIT.call_once(|| {
+        console_log::init_with_level(log::Level::Info).unwrap();
+    });
+    crate::utils::set_panic_hook();
+}
+
+/// Returns the number of scalars in `scalars_flat`.
+fn n_scalars(scalars_flat: &[u32]) -> Result<usize, MsmError> {
+    if scalars_flat.len() % 8 != 0 {
+        return Err(MsmError::InvalidLength {
+            name: "scalars",
+            len: scalars_flat.len(),
+            element_len: 8,
+        });
+    }
+    Ok(scalars_flat.len() / 8)
+}
+
+/// Checks that `scalars_flat` holds one scalar for each of `n_points` points.
+fn check_scalars(scalars_flat: &[u32], n_points: usize) -> Result<(), MsmError> {
+    let n_scalars = n_scalars(scalars_flat)?;
+    if n_scalars != n_points {
+        return Err(MsmError::LengthMismatch {
+            n_scalars,
+            n_points,
+        });
+    }
+    Ok(())
+}
+
 pub fn split<Split>(scalars_flat: &[u32]) -> Vec<u32>
 where
     Split: SplitImpl,
     Split::Output: Into<u32> + Copy,
     [(); Split::N_WINDOWS]: Sized,
 {
-    INIT.call_once(|| {
-        console_log::init_with_level(log::Level::Info).unwrap();
-    });
-    crate::utils::set_panic_hook();
+    init();
 
     debug_assert_eq!(scalars_flat.len() % 8, 0);
     let n_points = scalars_flat.len() / 8;
@@ -83,60 +216,522 @@ where
     result
 }
 
+/// Like `split`, but recodes each scalar into signed digits in
+/// `[-2^(w-1), 2^(w-1)]` so that only `2^(w-1) + 1` buckets are needed per
+/// window. Windows are ordered from the most significant to the least
+/// significant, same as `split`.
+pub fn split_signed<Split>(scalars_flat: &[u32]) -> Vec<i32>
+where
+    Split: SignedSplitImpl,
+    Split::Output: Into<i32> + Copy,
+    [(); Split::N_WINDOWS]: Sized,
+{
+    init();
+
+    debug_assert_eq!(scalars_flat.len() % 8, 0);
+    let n_points = scalars_flat.len() / 8;
+    let mut result = vec![0i32; n_points * Split::N_WINDOWS];
+    for i in 0..n_points {
+        let slice: &[u32; 8] = unsafe {
+            (&scalars_flat[8 * i..8 * i + 8])
+                .try_into()
+                .unwrap_unchecked()
+        };
+        let splitted_scalars = Split::split(slice);
+        for (j, splitted_scalar) in splitted_scalars.iter().enumerate() {
+            result[j * n_points + i] = (*splitted_scalar).into();
+        }
+    }
+    result
+}
+
+/// Same as `split`, but for any window size in
+/// `1..=MAX_RUNTIME_WINDOW_SIZE`, without a compiled splitter.
+pub fn split_runtime(scalars_flat: &[u32], window_size: usize) -> Result<Vec<u32>, MsmError> {
+    init();
+
+    if !(1..=MAX_RUNTIME_WINDOW_SIZE).contains(&window_size) {
+        return Err(MsmError::UnsupportedRuntimeWindowSize {
+            window_size: window_size as u32,
+            max: MAX_RUNTIME_WINDOW_SIZE as u32,
+        });
+    }
+    let n_points = n_scalars(scalars_flat)?;
+    let n_windows = n_runtime_windows(window_size);
+    let mut result = vec![0u32; n_points * n_windows];
+    let mut windows = vec![0u32; n_windows];
+    for i in 0..n_points {
+        let slice: &[u32; 8] = unsafe {
+            (&scalars_flat[8 * i..8 * i + 8])
+                .try_into()
+                .unwrap_unchecked()
+        };
+        split_scalar_runtime(slice, window_size, &mut windows);
+        for (j, window) in windows.iter().enumerate() {
+            result[j * n_points + i] = *window;
+        }
+    }
+    Ok(result)
+}
+
+/// Same as `split_signed`, but for any window size in
+/// `1..=MAX_RUNTIME_SIGNED_WINDOW_SIZE`, without a compiled splitter.
+pub fn split_signed_runtime(
+    scalars_flat: &[u32],
+    window_size: usize,
+) -> Result<Vec<i32>, MsmError> {
+    init();
+
+    if !(1..=MAX_RUNTIME_SIGNED_WINDOW_SIZE).contains(&window_size) {
+        return Err(MsmError::UnsupportedRuntimeWindowSize {
+            window_size: window_size as u32,
+            max: MAX_RUNTIME_SIGNED_WINDOW_SIZE as u32,
+        });
+    }
+    let n_points = n_scalars(scalars_flat)?;
+    let n_windows = n_runtime_signed_windows(window_size);
+    let mut result = vec![0i32; n_points * n_windows];
+    let mut digits = vec![0i32; n_windows];
+    for i in 0..n_points {
+        let slice: &[u32; 8] = unsafe {
+            (&scalars_flat[8 * i..8 * i + 8])
+                .try_into()
+                .unwrap_unchecked()
+        };
+        split_scalar_signed_runtime(slice, window_size, &mut digits);
+        for (j, digit) in digits.iter().enumerate() {
+            result[j * n_points + i] = *digit;
+        }
+    }
+    Ok(result)
+}
+
 use rayon::prelude::ParallelSlice;
 
-fn reduce_last<Split>(bucket_sums: Vec<EdwardsProjective>) -> Vec<u32>
+/// Computes the MSM with `msm_straus` if there are too few points for
+/// Pippenger's algorithm to pay off, including when there are none.
+fn msm_small<G: MsmCurve>(scalars_flat: &[u32], points: &[G]) -> Option<Vec<u32>> {
+    if points.len() >= SMALL_MSM_THRESHOLD {
+        return None;
+    }
+    Some(span("straus", || {
+        write_affine::<G>(&msm_straus(scalars_flat, points).into_affine())
+    }))
+}
+
+fn reduce_last<G: PointEncoding>(bucket_sums: Vec<G>, window_size: usize) -> Vec<u32> {
+    span("reduce", || {
+        let mut sum = G::zero();
+        for bucket_sum in bucket_sums {
+            for _ in 0..window_size {
+                sum.double_in_place();
+            }
+            sum += bucket_sum;
+        }
+        write_affine::<G>(&sum.into_affine())
+    })
+}
+
+/// Number of parts to split each window's points into. Parallelising over
+/// windows alone leaves threads idle when there are fewer windows than threads,
+/// so in that case each window is further split across threads, each with its
+/// own set of buckets. Merging the bucket sets costs `n_buckets` additions per
+/// extra part, so we never make a part smaller than that.
+fn n_window_parts(n_windows: usize, n_points: usize, n_buckets: usize) -> usize {
+    let n_threads = rayon::current_num_threads();
+    if n_windows >= n_threads {
+        return 1;
+    }
+    n_threads
+        .div_ceil(n_windows)
+        .min(n_points / n_buckets)
+        .max(1)
+}
+
+/// Buckets a single window split into `n_parts` parts, which are bucketed in
+/// parallel into thread-local buckets and merged afterward.
+fn bucket_window<G, D, P, F>(digits: &[D], points: &[P], n_parts: usize, bucket: F) -> Vec<G>
 where
-    Split: SplitImpl,
+    G: CurveGroup,
+    D: BucketDigit,
+    P: Sync,
+    F: Fn(&[D], &[P]) -> Vec<G> + Sync,
 {
-    let mut sum = EdwardsProjective::zero();
-    for bucket_sum in bucket_sums {
-        for _ in 0..Split::WINDOW_SIZE {
-            sum.double_in_place();
+    if n_parts <= 1 {
+        return bucket(digits, points);
+    }
+    let part_size = digits.len().div_ceil(n_parts);
+    digits
+        .par_chunks(part_size)
+        .zip(points.par_chunks(part_size))
+        .map(|(digits, points)| bucket(digits, points))
+        .reduce_with(|mut acc, other| {
+            for (sum, bucket) in acc.iter_mut().zip(other) {
+                *sum += bucket;
+            }
+            acc
+        })
+        .unwrap()
+}
+
+/// Base points of an MSM. The affine form of the points, used by
+/// `BucketStrategy::BatchAffine`, is only computed when first needed.
+struct Bases<G: CurveGroup> {
+    points: Vec<G>,
+    affine: OnceLock<Vec<G::Affine>>,
+}
+
+impl<G: CurveGroup> Bases<G> {
+    fn new(points: Vec<G>) -> Self {
+        Self {
+            points,
+            affine: OnceLock::new(),
         }
-        sum += bucket_sum;
     }
-    let result_affine = sum.into_affine();
-    let mut result_buf = vec![0u32; 16];
-    write_fq(&mut result_buf[0..8], &result_affine.x);
-    write_fq(&mut result_buf[8..16], &result_affine.y);
-    result_buf
+
+    fn affine(&self) -> &[G::Affine] {
+        self.affine.get_or_init(|| G::normalize_batch(&self.points))
+    }
 }
 
-fn msm_end_to_end<Split>(scalars_flat: &[u32], points_flat: &[u32]) -> Vec<u32>
+fn read_bases<G: PointEncoding>(points_flat: &[u32]) -> Result<Bases<G>, MsmError> {
+    Ok(Bases::new(span("decode", || {
+        read_points::<G>(points_flat)
+    })?))
+}
+
+/// Buckets the points of each window of `split` and computes the bucket sum of
+/// every window. `split` holds `n_windows` windows of `bases.points.len()`
+/// digits. The bucket sum of each window is recorded as a child span, as the
+/// windows are processed in parallel.
+fn bucket_windows<G, D>(
+    split: &[D],
+    bases: &Bases<G>,
+    n_windows: usize,
+    n_buckets: usize,
+    strategy: BucketStrategy,
+) -> Vec<G>
+where
+    G: MsmCurve,
+    D: BucketDigit,
+{
+    let chunk_size = split.len() / n_windows;
+    let n_parts = n_window_parts(n_windows, bases.points.len(), n_buckets);
+    span("bucket", || {
+        let (bucket_sums, sum_spans): (Vec<G>, Vec<_>) = match strategy {
+            BucketStrategy::Projective => split
+                .par_chunks(chunk_size)
+                .map(|chunk| {
+                    let bucket = bucket_window(chunk, &bases.points, n_parts, |digits, points| {
+                        bucket_cpu(digits, points, n_buckets)
+                    });
+                    spans::timed("bucket-sum", || bucket_sum_par(&bucket))
+                })
+                .unzip(),
+            BucketStrategy::BatchAffine => {
+                let points = bases.affine();
+                split
+                    .par_chunks(chunk_size)
+                    .map(|chunk| {
+                        let bucket = bucket_window(chunk, points, n_parts, |digits, points| {
+                            bucket_batch_affine::<G, D>(digits, points, n_buckets)
+                        });
+                        spans::timed("bucket-sum", || bucket_sum_par(&bucket))
+                    })
+                    .unzip()
+            }
+        };
+        spans::attach(sum_spans);
+        bucket_sums
+    })
+}
+
+fn msm_bases<G, Split>(
+    scalars_flat: &[u32],
+    bases: &Bases<G>,
+    strategy: BucketStrategy,
+) -> Result<Vec<u32>, MsmError>
 where
+    G: MsmCurve,
     Split: SplitImpl,
     Split::Output: Into<u32> + Copy,
     [(); Split::N_WINDOWS]: Sized,
 {
-    let split = split::<Split>(scalars_flat);
-    let chunk_size = split.len() / Split::N_WINDOWS;
-    let points = read_points(points_flat);
+    check_scalars(scalars_flat, bases.points.len())?;
+    if let Some(result) = msm_small(scalars_flat, &bases.points) {
+        return Ok(result);
+    }
+    let split = span("split", || split::<Split>(scalars_flat));
     let n_buckets = 1 << Split::WINDOW_SIZE;
-    let bucket_sums = split
-        .par_chunks(chunk_size)
-        .map(|chunk| bucket_sum_cpu(bucket_cpu(chunk, &points, n_buckets)))
-        .collect::<Vec<_>>();
-    reduce_last::<Split>(bucket_sums)
+    let bucket_sums = bucket_windows(&split, bases, Split::N_WINDOWS, n_buckets, strategy);
+    Ok(reduce_last(bucket_sums, Split::WINDOW_SIZE))
+}
+
+/// Same as `msm_bases`, but uses signed digits. This halves the number of
+/// buckets (and hence the cost of the running sum) at the cost of negating
+/// points on the fly, which is nearly free on both curve models we support.
+fn msm_bases_signed<G, Split>(
+    scalars_flat: &[u32],
+    bases: &Bases<G>,
+    strategy: BucketStrategy,
+) -> Result<Vec<u32>, MsmError>
+where
+    G: MsmCurve,
+    Split: SignedSplitImpl,
+    Split::Output: Into<i32> + Copy,
+    [(); Split::N_WINDOWS]: Sized,
+{
+    check_scalars(scalars_flat, bases.points.len())?;
+    if let Some(result) = msm_small(scalars_flat, &bases.points) {
+        return Ok(result);
+    }
+    let split = span("split", || split_signed::<Split>(scalars_flat));
+    let n_buckets = (1 << (Split::WINDOW_SIZE - 1)) + 1;
+    let bucket_sums = bucket_windows(&split, bases, Split::N_WINDOWS, n_buckets, strategy);
+    Ok(reduce_last(bucket_sums, Split::WINDOW_SIZE))
+}
+
+/// Largest window size of `msm_bases_runtime` and `msm_bases_signed_runtime`.
+/// The splitters support larger windows, but bucketing a window allocates
+/// `2^window_size` points, which is already gigabytes at this size.
+const MAX_RUNTIME_MSM_WINDOW_SIZE: usize = 24;
+
+fn check_runtime_window_size(window_size: usize) -> Result<(), MsmError> {
+    if !(1..=MAX_RUNTIME_MSM_WINDOW_SIZE).contains(&window_size) {
+        return Err(MsmError::UnsupportedRuntimeWindowSize {
+            window_size: window_size as u32,
+            max: MAX_RUNTIME_MSM_WINDOW_SIZE as u32,
+        });
+    }
+    Ok(())
+}
+
+/// Same as `msm_bases`, but splits the scalars with `split_runtime`. Supports
+/// window sizes in `1..=MAX_RUNTIME_MSM_WINDOW_SIZE`.
+fn msm_bases_runtime<G: MsmCurve>(
+    scalars_flat: &[u32],
+    window_size: usize,
+    bases: &Bases<G>,
+    strategy: BucketStrategy,
+) -> Result<Vec<u32>, MsmError> {
+    check_runtime_window_size(window_size)?;
+    check_scalars(scalars_flat, bases.points.len())?;
+    if let Some(result) = msm_small(scalars_flat, &bases.points) {
+        return Ok(result);
+    }
+    let split = span("split", || split_runtime(scalars_flat, window_size))?;
+    let n_buckets = 1 << window_size;
+    let n_windows = n_runtime_windows(window_size);
+    let bucket_sums = bucket_windows(&split, bases, n_windows, n_buckets, strategy);
+    Ok(reduce_last(bucket_sums, window_size))
+}
+
+/// Same as `msm_bases_signed`, but splits the scalars with
+/// `split_signed_runtime`.
+fn msm_bases_signed_runtime<G: MsmCurve>(
+    scalars_flat: &[u32],
+    window_size: usize,
+    bases: &Bases<G>,
+    strategy: BucketStrategy,
+) -> Result<Vec<u32>, MsmError> {
+    check_runtime_window_size(window_size)?;
+    check_scalars(scalars_flat, bases.points.len())?;
+    if let Some(result) = msm_small(scalars_flat, &bases.points) {
+        return Ok(result);
+    }
+    let split = span("split", || split_signed_runtime(scalars_flat, window_size))?;
+    let n_buckets = (1 << (window_size - 1)) + 1;
+    let n_windows = n_runtime_signed_windows(window_size);
+    let bucket_sums = bucket_windows(&split, bases, n_windows, n_buckets, strategy);
+    Ok(reduce_last(bucket_sums, window_size))
+}
+
+/// Returns the number of scalar vectors of length `n_points` in `scalars_flat`.
+/// The number is ambiguous without points, so that is an error.
+fn n_msms(scalars_flat: &[u32], n_points: usize) -> Result<usize, MsmError> {
+    let n_scalars = n_scalars(scalars_flat)?;
+    if n_points == 0 {
+        return Err(MsmError::EmptyInput);
+    }
+    if n_scalars % n_points != 0 {
+        return Err(MsmError::LengthMismatch {
+            n_scalars,
+            n_points,
+        });
+    }
+    Ok(n_scalars / n_points)
+}
+
+/// Computes several MSMs over the same bases in one pass. `scalars_flat` holds
+/// `k` scalar vectors of `bases.points.len()` scalars each, one after another.
+/// The windows of all `k` MSMs are bucketed together, and the `k` results are
+/// returned one after another in affine form. An empty `scalars_flat` gives no
+/// results.
+fn msm_multi<G, Split>(
+    scalars_flat: &[u32],
+    bases: &Bases<G>,
+    strategy: BucketStrategy,
+) -> Result<Vec<u32>, MsmError>
+where
+    G: MsmCurve,
+    Split: SplitImpl,
+    Split::Output: Into<u32> + Copy,
+    [(); Split::N_WINDOWS]: Sized,
+{
+    let n_msms = n_msms(scalars_flat, bases.points.len())?;
+    if n_msms == 0 {
+        return Ok(Vec::new());
+    }
+    let vector_len = 8 * bases.points.len();
+    if bases.points.len() < SMALL_MSM_THRESHOLD {
+        return Ok(scalars_flat
+            .chunks(vector_len)
+            .flat_map(|scalars_flat| msm_small(scalars_flat, &bases.points).unwrap())
+            .collect());
+    }
+    let split = span("split", || {
+        scalars_flat
+            .par_chunks(vector_len)
+            .map(split::<Split>)
+            .collect::<Vec<_>>()
+            .concat()
+    });
+    let n_buckets = 1 << Split::WINDOW_SIZE;
+    let bucket_sums = bucket_windows(
+        &split,
+        bases,
+        n_msms * Split::N_WINDOWS,
+        n_buckets,
+        strategy,
+    );
+    Ok(bucket_sums
+        .chunks(Split::N_WINDOWS)
+        .flat_map(|bucket_sums| reduce_last(bucket_sums.to_vec(), Split::WINDOW_SIZE))
+        .collect())
+}
+
+/// Same as `msm_multi`, but uses signed digits.
+fn msm_multi_signed<G, Split>(
+    scalars_flat: &[u32],
+    bases: &Bases<G>,
+    strategy: BucketStrategy,
+) -> Result<Vec<u32>, MsmError>
+where
+    G: MsmCurve,
+    Split: SignedSplitImpl,
+    Split::Output: Into<i32> + Copy,
+    [(); Split::N_WINDOWS]: Sized,
+{
+    let n_msms = n_msms(scalars_flat, bases.points.len())?;
+    if n_msms == 0 {
+        return Ok(Vec::new());
+    }
+    let vector_len = 8 * bases.points.len();
+    if bases.points.len() < SMALL_MSM_THRESHOLD {
+        return Ok(scalars_flat
+            .chunks(vector_len)
+            .flat_map(|scalars_flat| msm_small(scalars_flat, &bases.points).unwrap())
+            .collect());
+    }
+    let split = span("split", || {
+        scalars_flat
+            .par_chunks(vector_len)
+            .map(split_signed::<Split>)
+            .collect::<Vec<_>>()
+            .concat()
+    });
+    let n_buckets = (1 << (Split::WINDOW_SIZE - 1)) + 1;
+    let bucket_sums = bucket_windows(
+        &split,
+        bases,
+        n_msms * Split::N_WINDOWS,
+        n_buckets,
+        strategy,
+    );
+    Ok(bucket_sums
+        .chunks(Split::N_WINDOWS)
+        .flat_map(|bucket_sums| reduce_last(bucket_sums.to_vec(), Split::WINDOW_SIZE))
+        .collect())
 }
 
-fn inter_bucket_reduce<Split>(raw_buckets: &[u32]) -> Vec<u32>
+fn msm_end_to_end<G, Split>(
+    scalars_flat: &[u32],
+    points_flat: &[u32],
+    strategy: BucketStrategy,
+) -> Result<Vec<u32>, MsmError>
 where
+    G: MsmCurve,
     Split: SplitImpl,
+    Split::Output: Into<u32> + Copy,
+    [(); Split::N_WINDOWS]: Sized,
 {
+    let bases = read_bases::<G>(points_flat)?;
+    msm_bases::<G, Split>(scalars_flat, &bases, strategy)
+}
+
+fn msm_end_to_end_signed<G, Split>(
+    scalars_flat: &[u32],
+    points_flat: &[u32],
+    strategy: BucketStrategy,
+) -> Result<Vec<u32>, MsmError>
+where
+    G: MsmCurve,
+    Split: SignedSplitImpl,
+    Split::Output: Into<i32> + Copy,
+    [(); Split::N_WINDOWS]: Sized,
+{
+    let bases = read_bases::<G>(points_flat)?;
+    msm_bases_signed::<G, Split>(scalars_flat, &bases, strategy)
+}
+
+fn inter_bucket_reduce<G, Split>(raw_buckets: &[u32]) -> Result<Vec<u32>, MsmError>
+where
+    G: PointEncoding,
+    Split: SplitImpl,
+{
+    let window_len = Split::N_WINDOWS * G::N_U32S_PER_POINT;
+    if raw_buckets.len() % window_len != 0 {
+        return Err(MsmError::InvalidLength {
+            name: "raw_buckets",
+            len: raw_buckets.len(),
+            element_len: window_len,
+        });
+    }
+    if raw_buckets.is_empty() {
+        return Ok(write_affine::<G>(&G::zero().into_affine()));
+    }
     let chunk_size = raw_buckets.len() / Split::N_WINDOWS;
     let bucket_sums = raw_buckets
         .par_chunks(chunk_size)
-        .map(|chunk| bucket_sum_cpu(read_points(chunk)))
-        .collect::<Vec<_>>();
-    reduce_last::<Split>(bucket_sums)
+        .map(|chunk| read_points::<G>(chunk).map(|bucket| bucket_sum_par(&bucket)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(reduce_last(bucket_sums, Split::WINDOW_SIZE))
 }
 
-fn inter_bucket_reduce_last<Split>(raw_buckets: &[u32]) -> Vec<u32>
+fn inter_bucket_reduce_last<G, Split>(raw_buckets: &[u32]) -> Result<Vec<u32>, MsmError>
 where
+    G: PointEncoding,
     Split: SplitImpl,
 {
-    reduce_last::<Split>(read_points(raw_buckets))
+    Ok(reduce_last::<G>(
+        read_points(raw_buckets)?,
+        Split::WINDOW_SIZE,
+    ))
+}
+
+fn add_affine<G: PointEncoding>(a: &[u32], b: &[u32]) -> Result<Vec<u32>, MsmError> {
+    for buf in [a, b] {
+        if buf.len() != G::N_U32S_PER_AFFINE {
+            return Err(MsmError::UnexpectedLength {
+                name: "affine point",
+                len: buf.len(),
+                expected_len: G::N_U32S_PER_AFFINE,
+            });
+        }
+    }
+    let a = G::read_affine(a).ok_or(MsmError::NonCanonicalFieldElement { index: 0 })?;
+    let b = G::read_affine(b).ok_or(MsmError::NonCanonicalFieldElement { index: 1 })?;
+    Ok(write_affine::<G>(&(a + b).into_affine()))
 }
 
 /// Run a (internally parallel) computation with a given number of idle threads.
@@ -168,88 +763,357 @@ where
     ret
 }
 
-macro_rules! define_msm_functions {
+macro_rules! define_split_functions {
     ($($w:expr),*) => {
+        /// Window sizes with compiled splitters.
+        pub const WINDOW_SIZES: &[u32] = &[$($w),*];
+
         $(paste! {
             #[wasm_bindgen]
-            pub fn [<split_ $w>](scalars_flat: &[u32]) -> Vec<u32> {
-                split::<[<Split $w>]>(scalars_flat)
+            pub fn [<split_ $w>](scalars_flat: &[u32]) -> Result<Vec<u32>, JsError> {
+                n_scalars(scalars_flat)?;
+                Ok(split::<[<Split $w>]>(scalars_flat))
             }
 
             #[wasm_bindgen]
-            pub fn [<msm_end_to_end_ $w>](scalars_flat: &[u32], points_flat: &[u32]) -> Vec<u32> {
-                msm_end_to_end::<[<Split $w>]>(scalars_flat, points_flat)
+            pub fn [<split_signed_ $w>](scalars_flat: &[u32]) -> Result<Vec<i32>, JsError> {
+                n_scalars(scalars_flat)?;
+                Ok(split_signed::<[<Split $w S>]>(scalars_flat))
             }
+        })*
 
+        paste!{
             #[wasm_bindgen]
-            pub fn [<inter_bucket_reduce_ $w>](raw_buckets: &[u32]) -> Vec<u32> {
-                inter_bucket_reduce::<[<Split $w>]>(raw_buckets)
+            pub fn split_dynamic(window_size: u32, scalars_flat: &[u32]) -> Result<Vec<u32>, JsError> {
+                match window_size {
+                    $( $w => {
+                        n_scalars(scalars_flat)?;
+                        Ok(split::<[<Split $w>]>(scalars_flat))
+                    } )*
+                    _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
+                }
+            }
+
+            /// Splits with any window size, including ones without a compiled
+            /// splitter.
+            #[wasm_bindgen(js_name = split_runtime)]
+            pub fn split_runtime_export(window_size: u32, scalars_flat: &[u32]) -> Result<Vec<u32>, JsError> {
+                Ok(split_runtime(scalars_flat, window_size as usize)?)
+            }
+
+            #[wasm_bindgen(js_name = split_signed_runtime)]
+            pub fn split_signed_runtime_export(window_size: u32, scalars_flat: &[u32]) -> Result<Vec<i32>, JsError> {
+                Ok(split_signed_runtime(scalars_flat, window_size as usize)?)
             }
 
             #[wasm_bindgen]
-            pub fn [<inter_bucket_reduce_last_ $w>](raw_buckets: &[u32]) -> Vec<u32> {
-                inter_bucket_reduce_last::<[<Split $w>]>(raw_buckets)
+            pub fn split_signed_dynamic(window_size: u32, scalars_flat: &[u32]) -> Result<Vec<i32>, JsError> {
+                match window_size {
+                    $( $w => {
+                        n_scalars(scalars_flat)?;
+                        Ok(split_signed::<[<Split $w S>]>(scalars_flat))
+                    } )*
+                    _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
+                }
+            }
+        }
+    };
+}
+
+macro_rules! define_msm_end_to_end_any {
+    ($($w:expr),*) => {
+        paste! {
+            /// Runs `msm_end_to_end` or `msm_end_to_end_signed` with any window
+            /// size, using the compiled splitter if there is one and the runtime
+            /// splitter otherwise.
+            fn msm_end_to_end_any<G: MsmCurve>(
+                window_size: usize,
+                signed: bool,
+                strategy: BucketStrategy,
+                scalars_flat: &[u32],
+                points_flat: &[u32],
+            ) -> Result<Vec<u32>, MsmError> {
+                match (window_size, signed) {
+                    $(
+                        ($w, false) => msm_end_to_end::<G, [<Split $w>]>(scalars_flat, points_flat, strategy),
+                        ($w, true) => msm_end_to_end_signed::<G, [<Split $w S>]>(scalars_flat, points_flat, strategy),
+                    )*
+                    (_, false) => msm_bases_runtime(scalars_flat, window_size, &read_bases::<G>(points_flat)?, strategy),
+                    (_, true) => msm_bases_signed_runtime(scalars_flat, window_size, &read_bases::<G>(points_flat)?, strategy),
+                }
+            }
+        }
+    };
+}
+
+/// Defines the wasm exports for a given curve. Exports for curves other than
+/// the default one (Edwards BLS12-377) are prefixed with the curve name, e.g.,
+/// `bandersnatch_msm_end_to_end_dynamic`.
+macro_rules! define_msm_functions {
+    ($curve:ident => $g:ty; $($w:expr),*) => {
+        define_msm_functions!(@impl [$curve] $g; $($w),*);
+    };
+    ($g:ty; $($w:expr),*) => {
+        define_msm_functions!(@impl [] $g; $($w),*);
+    };
+    (@impl $prefix:tt $g:ty; $($w:expr),*) => {
+        $(define_msm_functions!(@window $prefix $g; $w);)*
+        define_msm_functions!(@dynamic $prefix $g; $($w),*);
+    };
+    (@window [$($curve:ident)?] $g:ty; $w:expr) => {
+        paste! {
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? msm_end_to_end_ $w>](scalars_flat: &[u32], points_flat: &[u32]) -> Result<Vec<u32>, JsError> {
+                Ok(msm_end_to_end::<$g, [<Split $w>]>(scalars_flat, points_flat, BucketStrategy::Projective)?)
+            }
+
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? msm_end_to_end_signed_ $w>](scalars_flat: &[u32], points_flat: &[u32]) -> Result<Vec<u32>, JsError> {
+                Ok(msm_end_to_end_signed::<$g, [<Split $w S>]>(scalars_flat, points_flat, BucketStrategy::Projective)?)
+            }
+
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? inter_bucket_reduce_ $w>](raw_buckets: &[u32]) -> Result<Vec<u32>, JsError> {
+                Ok(inter_bucket_reduce::<$g, [<Split $w>]>(raw_buckets)?)
             }
-        })*
 
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? inter_bucket_reduce_last_ $w>](raw_buckets: &[u32]) -> Result<Vec<u32>, JsError> {
+                Ok(inter_bucket_reduce_last::<$g, [<Split $w>]>(raw_buckets)?)
+            }
+        }
+    };
+    (@dynamic [$($curve:ident)?] $g:ty; $($w:expr),*) => {
         paste!{
             #[wasm_bindgen]
-            pub fn split_dynamic(window_size: u32, scalars_flat: &[u32]) -> Vec<u32> {
+            pub fn [<$($curve _)? msm_end_to_end_dynamic>](window_size: u32, scalars_flat: &[u32], points_flat: &[u32]) -> Result<Vec<u32>, JsError> {
                 match window_size {
-                    $( $w => split::<[<Split $w>]>(scalars_flat), )*
-                    _ => panic!("Unsupported window size: {}", window_size),
+                    $( $w => Ok(msm_end_to_end::<$g, [<Split $w>]>(scalars_flat, points_flat, BucketStrategy::Projective)?), )*
+                    _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
                 }
             }
 
             #[wasm_bindgen]
-            pub fn msm_end_to_end_dynamic(window_size: u32, scalars_flat: &[u32], points_flat: &[u32]) -> Vec<u32> {
+            pub fn [<$($curve _)? msm_end_to_end_signed_dynamic>](window_size: u32, scalars_flat: &[u32], points_flat: &[u32]) -> Result<Vec<u32>, JsError> {
                 match window_size {
-                    $( $w => msm_end_to_end::<[<Split $w>]>(scalars_flat, points_flat), )*
-                    _ => panic!("Unsupported window size: {}", window_size),
+                    $( $w => Ok(msm_end_to_end_signed::<$g, [<Split $w S>]>(scalars_flat, points_flat, BucketStrategy::Projective)?), )*
+                    _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
                 }
             }
 
             #[wasm_bindgen]
-            pub fn msm_end_to_end_dynamic_with_idle(window_size: u32, scalars_flat: &[u32], points_flat: &[u32], num_idle_threads: usize) -> Vec<u32> {
-                run_with_idle(num_idle_threads, ||
-                    msm_end_to_end_dynamic(window_size, scalars_flat, points_flat)
-                )
+            pub fn [<$($curve _)? msm_end_to_end_dynamic_with_strategy>](window_size: u32, strategy: BucketStrategy, scalars_flat: &[u32], points_flat: &[u32]) -> Result<Vec<u32>, JsError> {
+                match window_size {
+                    $( $w => Ok(msm_end_to_end::<$g, [<Split $w>]>(scalars_flat, points_flat, strategy)?), )*
+                    _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
+                }
             }
 
             #[wasm_bindgen]
-            pub fn inter_bucket_reduce_dynamic(window_size: u32, raw_buckets: &[u32]) -> Vec<u32> {
+            pub fn [<$($curve _)? msm_end_to_end_signed_dynamic_with_strategy>](window_size: u32, strategy: BucketStrategy, scalars_flat: &[u32], points_flat: &[u32]) -> Result<Vec<u32>, JsError> {
                 match window_size {
-                    $( $w => inter_bucket_reduce::<[<Split $w>]>(raw_buckets), )*
-                    _ => panic!("Unsupported window size: {}", window_size),
+                    $( $w => Ok(msm_end_to_end_signed::<$g, [<Split $w S>]>(scalars_flat, points_flat, strategy)?), )*
+                    _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
                 }
             }
 
             #[wasm_bindgen]
-            pub fn inter_bucket_reduce_last_dynamic(window_size: u32, raw_buckets: &[u32]) -> Vec<u32> {
+            pub fn [<$($curve _)? msm_multi_dynamic>](window_size: u32, scalars_flat: &[u32], points_flat: &[u32]) -> Result<Vec<u32>, JsError> {
+                let bases = read_bases::<$g>(points_flat)?;
                 match window_size {
-                    $( $w => inter_bucket_reduce_last::<[<Split $w>]>(raw_buckets), )*
-                    _ => panic!("Unsupported window size: {}", window_size),
+                    $( $w => Ok(msm_multi::<$g, [<Split $w>]>(scalars_flat, &bases, BucketStrategy::Projective)?), )*
+                    _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
+                }
+            }
+
+            /// Same as `msm_end_to_end_dynamic_with_strategy` (or its signed
+            /// version), but also returns the time spent in each phase. Supports
+            /// any window size up to 24 through the runtime splitter.
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? msm_end_to_end_profiled>](window_size: u32, signed: bool, strategy: BucketStrategy, scalars_flat: &[u32], points_flat: &[u32]) -> Result<MsmProfile, JsError> {
+                let (result, spans) = spans::record(|| {
+                    msm_end_to_end_any::<$g>(window_size as usize, signed, strategy, scalars_flat, points_flat)
+                });
+                Ok(MsmProfile { result: result?, spans })
+            }
+
+            /// Same as `msm_end_to_end_dynamic`, but supports any window size up
+            /// to 24 through the runtime splitter.
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? msm_end_to_end_runtime>](window_size: u32, scalars_flat: &[u32], points_flat: &[u32]) -> Result<Vec<u32>, JsError> {
+                let bases = read_bases::<$g>(points_flat)?;
+                Ok(msm_bases_runtime(scalars_flat, window_size as usize, &bases, BucketStrategy::Projective)?)
+            }
+
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? msm_end_to_end_signed_runtime>](window_size: u32, scalars_flat: &[u32], points_flat: &[u32]) -> Result<Vec<u32>, JsError> {
+                let bases = read_bases::<$g>(points_flat)?;
+                Ok(msm_bases_signed_runtime(scalars_flat, window_size as usize, &bases, BucketStrategy::Projective)?)
+            }
+
+            /// Same as `msm_end_to_end_dynamic`, with the window size picked by
+            /// `choose_window_size`.
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? msm_end_to_end_auto>](scalars_flat: &[u32], points_flat: &[u32]) -> Result<Vec<u32>, JsError> {
+                let n = points_flat.len() / <$g as PointEncoding>::N_U32S_PER_POINT;
+                let window_size = choose_window_size(n, rayon::current_num_threads());
+                [<$($curve _)? msm_end_to_end_dynamic>](window_size, scalars_flat, points_flat)
+            }
+
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? msm_end_to_end_dynamic_with_idle>](window_size: u32, scalars_flat: &[u32], points_flat: &[u32], num_idle_threads: usize) -> Result<Vec<u32>, JsError> {
+                // `JsError` is not `Send`, so only convert the error once back
+                // on this thread.
+                Ok(run_with_idle(num_idle_threads, || match window_size {
+                    $( $w => msm_end_to_end::<$g, [<Split $w>]>(scalars_flat, points_flat, BucketStrategy::Projective), )*
+                    _ => Err(MsmError::UnsupportedWindowSize(window_size)),
+                })?)
+            }
+
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? inter_bucket_reduce_dynamic>](window_size: u32, raw_buckets: &[u32]) -> Result<Vec<u32>, JsError> {
+                match window_size {
+                    $( $w => Ok(inter_bucket_reduce::<$g, [<Split $w>]>(raw_buckets)?), )*
+                    _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? inter_bucket_reduce_last_dynamic>](window_size: u32, raw_buckets: &[u32]) -> Result<Vec<u32>, JsError> {
+                match window_size {
+                    $( $w => Ok(inter_bucket_reduce_last::<$g, [<Split $w>]>(raw_buckets)?), )*
+                    _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
+                }
+            }
+
+            /// A set of base points that is parsed once and reused across
+            /// MSMs.
+            #[wasm_bindgen]
+            pub struct [<$($curve:camel)? BaseSet>] {
+                bases: Bases<$g>,
+                /// Bucketing strategy used by `msm` and `msm_signed`.
+                pub strategy: BucketStrategy,
+            }
+
+            #[wasm_bindgen]
+            impl [<$($curve:camel)? BaseSet>] {
+                #[wasm_bindgen(constructor)]
+                pub fn new(points_flat: &[u32]) -> Result<Self, JsError> {
+                    Ok(Self {
+                        bases: read_bases::<$g>(points_flat)?,
+                        strategy: BucketStrategy::Projective,
+                    })
+                }
+
+                /// Same as the constructor, but also validates the points. See
+                /// `validate.rs`.
+                pub fn validated(points_flat: &[u32]) -> Result<[<$($curve:camel)? BaseSet>], JsError> {
+                    let points = read_points::<$g>(points_flat)?;
+                    validate::validate_points(&points)?;
+                    Ok(Self {
+                        bases: Bases::new(points),
+                        strategy: BucketStrategy::Projective,
+                    })
+                }
+
+                #[wasm_bindgen(getter)]
+                pub fn length(&self) -> usize {
+                    self.bases.points.len()
+                }
+
+                pub fn msm(&self, scalars_flat: &[u32], window_size: u32) -> Result<Vec<u32>, JsError> {
+                    match window_size {
+                        $( $w => Ok(msm_bases::<$g, [<Split $w>]>(scalars_flat, &self.bases, self.strategy)?), )*
+                        _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
+                    }
+                }
+
+                pub fn msm_signed(&self, scalars_flat: &[u32], window_size: u32) -> Result<Vec<u32>, JsError> {
+                    match window_size {
+                        $( $w => Ok(msm_bases_signed::<$g, [<Split $w S>]>(scalars_flat, &self.bases, self.strategy)?), )*
+                        _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
+                    }
+                }
+
+                /// Computes one MSM per scalar vector in `scalars_flat`, which
+                /// holds the vectors one after another.
+                pub fn msm_multi(&self, scalars_flat: &[u32], window_size: u32) -> Result<Vec<u32>, JsError> {
+                    match window_size {
+                        $( $w => Ok(msm_multi::<$g, [<Split $w>]>(scalars_flat, &self.bases, self.strategy)?), )*
+                        _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
+                    }
+                }
+
+                pub fn msm_multi_signed(&self, scalars_flat: &[u32], window_size: u32) -> Result<Vec<u32>, JsError> {
+                    match window_size {
+                        $( $w => Ok(msm_multi_signed::<$g, [<Split $w S>]>(scalars_flat, &self.bases, self.strategy)?), )*
+                        _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
+                    }
                 }
             }
+
+            /// Precomputed tables for fixed-base MSMs over a set of bases. See
+            /// `fixed_base.rs` for the meaning of `stride`.
+            #[wasm_bindgen]
+            pub struct [<$($curve:camel)? FixedBaseTable>](FixedBase<$g>);
+
+            #[wasm_bindgen]
+            impl [<$($curve:camel)? FixedBaseTable>] {
+                #[wasm_bindgen(constructor)]
+                pub fn new(window_size: u32, stride: u32, points_flat: &[u32]) -> Result<Self, JsError> {
+                    let points = read_points::<$g>(points_flat)?;
+                    match window_size {
+                        $( $w => Ok(Self(FixedBase::new::<[<Split $w S>]>(&points, stride as usize))), )*
+                        _ => Err(MsmError::UnsupportedWindowSize(window_size).into()),
+                    }
+                }
+
+                pub fn msm(&self, scalars_flat: &[u32]) -> Result<Vec<u32>, JsError> {
+                    match self.0.window_size() {
+                        $( $w => Ok(self.0.msm::<[<Split $w S>]>(scalars_flat)?), )*
+                        window_size => Err(MsmError::UnsupportedWindowSize(window_size as u32).into()),
+                    }
+                }
+            }
+
+            /// Checks that every point is on the curve and in the prime-order
+            /// subgroup, throwing an error naming the first invalid one.
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? validate_points>](points_flat: &[u32]) -> Result<(), JsError> {
+                Ok(validate::validate_points(&read_points::<$g>(points_flat)?)?)
+            }
+
+            #[wasm_bindgen]
+            pub fn [<$($curve _)? point_add_affine>](a: &[u32], b: &[u32]) -> Result<Vec<u32>, JsError> {
+                Ok(add_affine::<$g>(a, b)?)
+            }
         }
     };
 }
 
-define_msm_functions!(8, 9, 10, 11, 12, 13, 14, 15, 16, 20);
-
-#[wasm_bindgen]
-pub fn point_add_affine(a: &[u32], b: &[u32]) -> Vec<u32> {
-    assert_eq!(a.len(), 16);
-    assert_eq!(b.len(), 16);
-    let a = EdwardsAffine::new_unchecked(read_fq(&a[0..8]), read_fq(&a[8..16]));
-    let b = EdwardsAffine::new_unchecked(read_fq(&b[0..8]), read_fq(&b[8..16]));
-    let c = (a + b).into_affine();
-    let mut result_buf = vec![0u32; 16];
-    write_fq(&mut result_buf[0..8], &c.x);
-    write_fq(&mut result_buf[8..16], &c.y);
-    result_buf
-}
+with_window_sizes!(define_split_functions!());
+with_window_sizes!(define_msm_end_to_end_any!());
+with_window_sizes!(define_msm_functions!(EdwardsProjective;));
+with_window_sizes!(define_msm_functions!(bandersnatch => BandersnatchProjective;));
+with_window_sizes!(define_msm_functions!(ed_on_bn254 => BabyJubjubProjective;));
+with_window_sizes!(define_msm_functions!(bls12_377_g1 => Bls12_377G1Projective;));
+with_window_sizes!(define_msm_functions!(bls12_377_g2 => Bls12_377G2Projective;));
+#[cfg(feature = "bn254")]
+with_window_sizes!(define_msm_functions!(bn254_g1 => Bn254G1Projective;));
+#[cfg(feature = "bls12-381")]
+with_window_sizes!(define_msm_functions!(bls12_381_g1 => Bls12_381G1Projective;));
 
 // WASM bindings
 
+pub use spans::{MsmProfile, Span};
 pub use wasm_bindgen_rayon::init_thread_pool;
+pub use window::{calibrate_window_size_model, choose_window_size};
+
+// Declared after the window size macros, which it uses to dispatch to the
+// compiled splitters.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native;
+
+// Declared last so that the tests can use the macros above.
+#[cfg(test)]
+mod tests;
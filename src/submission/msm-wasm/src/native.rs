@@ -0,0 +1,99 @@
+//! Native entry points for running and profiling the CPU pipeline outside the
+//! browser, used by the `msm-cli` binary and the benchmarks. Everything here
+//! works on Edwards BLS12-377 points.
+
+use ark_ed_on_bls12_377::{EdwardsAffine, EdwardsProjective};
+
+use crate::bytes::{write_points, PointEncoding};
+use crate::error::MsmError;
+use crate::spans::{self, Span};
+use crate::split::*;
+use crate::{msm_end_to_end_any, split_runtime, split_signed_runtime, BucketStrategy};
+
+/// How `msm_end_to_end_timed` runs the MSM.
+#[derive(Clone, Copy, Debug)]
+pub struct MsmOptions {
+    pub window_size: usize,
+    /// Use signed digits, like `msm_end_to_end_signed`.
+    pub signed: bool,
+    pub strategy: BucketStrategy,
+}
+
+/// Serializes points in the input format of `msm_end_to_end`.
+pub fn points_to_flat(points: &[EdwardsProjective]) -> Vec<u32> {
+    write_points(points)
+}
+
+/// Same as `msm_end_to_end_profiled`, but returns the result as a point.
+pub fn msm_end_to_end_timed(
+    scalars_flat: &[u32],
+    points_flat: &[u32],
+    options: MsmOptions,
+) -> Result<(EdwardsAffine, Vec<Span>), MsmError> {
+    let (result, spans) = spans::record(|| {
+        msm_end_to_end_any::<EdwardsProjective>(
+            options.window_size,
+            options.signed,
+            options.strategy,
+            scalars_flat,
+            points_flat,
+        )
+    });
+    let result = EdwardsProjective::read_affine(&result?).expect("results are canonical");
+    Ok((result, spans))
+}
+
+// The individual phases of the pipeline.
+
+pub fn read_points(points_flat: &[u32]) -> Result<Vec<EdwardsProjective>, MsmError> {
+    crate::bytes::read_points(points_flat)
+}
+
+/// Buckets a single window of digits sequentially. Every digit must be smaller
+/// than `n_buckets`.
+pub fn bucket_cpu(
+    digits: &[u32],
+    points: &[EdwardsProjective],
+    n_buckets: usize,
+) -> Vec<EdwardsProjective> {
+    crate::bucket_cpu(digits, points, n_buckets)
+}
+
+pub fn bucket_sum_cpu(bucket: Vec<EdwardsProjective>) -> EdwardsProjective {
+    crate::bucket_sum_cpu(bucket)
+}
+
+/// The parallel bucket sum used by the pipeline.
+pub fn bucket_sum_par(bucket: &[EdwardsProjective]) -> EdwardsProjective {
+    crate::bucket_sum_par(bucket)
+}
+
+pub fn reduce_last(bucket_sums: Vec<EdwardsProjective>, window_size: usize) -> Vec<u32> {
+    crate::reduce_last(bucket_sums, window_size)
+}
+
+macro_rules! define_compiled_splits {
+    ($($w:expr),*) => {
+        paste::paste! {
+            /// Same as `split_runtime`, but uses the compiled splitter if there is
+            /// one for `window_size`.
+            pub fn split(scalars_flat: &[u32], window_size: usize) -> Result<Vec<u32>, MsmError> {
+                match window_size {
+                    $( $w => Ok(crate::split::<[<Split $w>]>(scalars_flat)), )*
+                    _ => split_runtime(scalars_flat, window_size),
+                }
+            }
+
+            /// Same as `split_signed_runtime`, but uses the compiled splitter if
+            /// there is one for `window_size`.
+            pub fn split_signed(scalars_flat: &[u32], window_size: usize) -> Result<Vec<i32>, MsmError> {
+                match window_size {
+                    $( $w => Ok(crate::split_signed::<[<Split $w S>]>(scalars_flat)), )*
+                    _ => split_signed_runtime(scalars_flat, window_size),
+                }
+            }
+        }
+    };
+}
+
+with_window_sizes!(define_compiled_splits!());
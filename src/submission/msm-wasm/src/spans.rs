@@ -0,0 +1,146 @@
+//! Nested timings of the phases of a call, returned to JS alongside its
+//! result.
+//!
+//! `record` collects the spans opened with `span` on the current thread while
+//! it runs a call. Outside `record`, `span` just runs its closure, so the
+//! pipeline is instrumented unconditionally. Spans are only opened by the
+//! thread driving the pipeline: work on rayon threads is timed with `timed`
+//! instead, and the resulting spans are attached to the open span with
+//! `attach` once back on the driving thread. Such spans may overlap.
+
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+
+use crate::utils::now_ms;
+
+/// A timed phase. Times are in milliseconds. While recording, `start_ms` is
+/// absolute; `record` makes it relative to the start of the call.
+#[derive(Clone, Debug)]
+pub struct Span {
+    pub name: &'static str,
+    pub start_ms: f64,
+    pub duration_ms: f64,
+    pub children: Vec<Span>,
+}
+
+impl Span {
+    fn open(name: &'static str) -> Self {
+        Self {
+            name,
+            start_ms: now_ms(),
+            duration_ms: 0.0,
+            children: Vec::new(),
+        }
+    }
+
+    fn close(&mut self) {
+        self.duration_ms = now_ms() - self.start_ms;
+    }
+
+    fn rebase(&mut self, origin_ms: f64) {
+        self.start_ms -= origin_ms;
+        for child in &mut self.children {
+            child.rebase(origin_ms);
+        }
+    }
+
+    /// Converts the span to a `{ name, start, duration, children }` object.
+    fn to_js(&self) -> JsValue {
+        let object = js_sys::Object::new();
+        let children = self
+            .children
+            .iter()
+            .map(Span::to_js)
+            .collect::<js_sys::Array>();
+        for (key, value) in [
+            ("name", JsValue::from_str(self.name)),
+            ("start", self.start_ms.into()),
+            ("duration", self.duration_ms.into()),
+            ("children", children.into()),
+        ] {
+            js_sys::Reflect::set(&object, &key.into(), &value).unwrap();
+        }
+        object.into()
+    }
+}
+
+thread_local! {
+    /// The spans that are open on this thread, outermost first. The first one
+    /// is the root opened by `record`.
+    static OPEN_SPANS: RefCell<Vec<Span>> = RefCell::new(Vec::new());
+}
+
+/// Runs `f` and returns its result together with the spans opened during it,
+/// with start times relative to the start of `f`.
+pub fn record<R>(f: impl FnOnce() -> R) -> (R, Vec<Span>) {
+    OPEN_SPANS.with(|spans| spans.borrow_mut().push(Span::open("")));
+    let result = f();
+    let mut root = OPEN_SPANS.with(|spans| spans.borrow_mut().pop().unwrap());
+    for child in &mut root.children {
+        child.rebase(root.start_ms);
+    }
+    (result, root.children)
+}
+
+/// Runs `f` in a span named `name`, nested in the innermost open span.
+pub fn span<R>(name: &'static str, f: impl FnOnce() -> R) -> R {
+    let recording = OPEN_SPANS.with(|spans| {
+        let mut spans = spans.borrow_mut();
+        if spans.is_empty() {
+            return false;
+        }
+        spans.push(Span::open(name));
+        true
+    });
+    let result = f();
+    if recording {
+        OPEN_SPANS.with(|spans| {
+            let mut spans = spans.borrow_mut();
+            let mut span = spans.pop().unwrap();
+            span.close();
+            spans.last_mut().unwrap().children.push(span);
+        });
+    }
+    result
+}
+
+/// Runs `f` and returns a span named `name` covering it, to be attached later.
+/// Can be called from any thread.
+pub fn timed<R>(name: &'static str, f: impl FnOnce() -> R) -> (R, Span) {
+    let mut span = Span::open(name);
+    let result = f();
+    span.close();
+    (result, span)
+}
+
+/// Adds spans from `timed` to the innermost open span, if recording.
+pub fn attach(children: Vec<Span>) {
+    OPEN_SPANS.with(|spans| {
+        if let Some(span) = spans.borrow_mut().last_mut() {
+            span.children.extend(children);
+        }
+    });
+}
+
+/// The result of an MSM, together with the spans recorded while computing it.
+#[wasm_bindgen]
+pub struct MsmProfile {
+    pub(crate) result: Vec<u32>,
+    pub(crate) spans: Vec<Span>,
+}
+
+#[wasm_bindgen]
+impl MsmProfile {
+    #[wasm_bindgen(getter)]
+    pub fn result(&self) -> Vec<u32> {
+        self.result.clone()
+    }
+
+    /// The top-level spans as `{ name, start, duration, children }` objects,
+    /// with times in milliseconds relative to the start of the call.
+    #[wasm_bindgen(getter)]
+    pub fn spans(&self) -> js_sys::Array {
+        self.spans.iter().map(Span::to_js).collect()
+    }
+}
@@ -14,13 +14,78 @@ pub trait SplitImpl {
     fn split(scalar: &[u32; 8]) -> [Self::Output; Self::N_WINDOWS];
 }
 
-define_msm_scalar_splitter! { Split8:  [u32; 8] -> [ 8u32] }
-define_msm_scalar_splitter! { Split9:  [u32; 8] -> [ 9u32] }
-define_msm_scalar_splitter! { Split10: [u32; 8] -> [10u32] }
-define_msm_scalar_splitter! { Split11: [u32; 8] -> [11u32] }
-define_msm_scalar_splitter! { Split12: [u32; 8] -> [12u32] }
-define_msm_scalar_splitter! { Split13: [u32; 8] -> [13u32] }
-define_msm_scalar_splitter! { Split14: [u32; 8] -> [14u32] }
-define_msm_scalar_splitter! { Split15: [u32; 8] -> [15u32] }
-define_msm_scalar_splitter! { Split16: [u32; 8] -> [16u32] }
-define_msm_scalar_splitter! { Split20: [u32; 8] -> [20u32] }
+/// Trait for recoding a multi-limb big scalar into signed digits in
+/// `[-2^(WINDOW_SIZE-1), 2^(WINDOW_SIZE-1)]`. This may need one more window
+/// than `SplitImpl`, as the carry out of the top window needs somewhere to go.
+pub trait SignedSplitImpl {
+    /// Window size in bits.
+    const WINDOW_SIZE: usize;
+    /// Number of windows.
+    const N_WINDOWS: usize;
+    /// Numeric type of each window.
+    type Output;
+
+    fn split(scalar: &[u32; 8]) -> [Self::Output; Self::N_WINDOWS];
+}
+
+// Defines `SplitN` and `SplitNS` for every window size enabled through a
+// `window-N` feature. See `build.rs`.
+include!(concat!(env!("OUT_DIR"), "/splitters.rs"));
+
+/// Largest window size supported by `split_scalar_runtime`.
+pub const MAX_RUNTIME_WINDOW_SIZE: usize = 32;
+/// Largest window size supported by `split_scalar_signed_runtime`. Signed
+/// digits must fit in an `i32`.
+pub const MAX_RUNTIME_SIGNED_WINDOW_SIZE: usize = 31;
+
+/// Number of windows `split_scalar_runtime` produces.
+pub const fn n_runtime_windows(window_size: usize) -> usize {
+    (256 + window_size - 1) / window_size
+}
+
+/// Number of windows `split_scalar_signed_runtime` produces.
+pub const fn n_runtime_signed_windows(window_size: usize) -> usize {
+    (256 + window_size) / window_size
+}
+
+/// Extracts bits `[offset, offset + window_size)` of a big-endian scalar.
+/// Bits past the end of the scalar are zero.
+#[inline(always)]
+fn extract_window(scalar: &[u32; 8], offset: usize, window_size: usize) -> u64 {
+    let limb = offset / 32;
+    if limb >= 8 {
+        return 0;
+    }
+    let lo = scalar[7 - limb] as u64;
+    let hi = if limb < 7 { scalar[6 - limb] as u64 } else { 0 };
+    (((hi << 32) | lo) >> (offset % 32)) & ((1u64 << window_size) - 1)
+}
+
+/// Same as `SplitImpl::split`, but with a window size only known at runtime.
+/// `windows` must hold `n_runtime_windows(window_size)` elements, which are
+/// written from the most significant one.
+#[inline]
+pub fn split_scalar_runtime(scalar: &[u32; 8], window_size: usize, windows: &mut [u32]) {
+    debug_assert!((1..=MAX_RUNTIME_WINDOW_SIZE).contains(&window_size));
+    let n_windows = windows.len();
+    for (i, window) in windows.iter_mut().enumerate() {
+        let offset = (n_windows - 1 - i) * window_size;
+        *window = extract_window(scalar, offset, window_size) as u32;
+    }
+}
+
+/// Same as `SignedSplitImpl::split`, but with a window size only known at
+/// runtime. `digits` must hold `n_runtime_signed_windows(window_size)`
+/// elements, which are written from the most significant one.
+#[inline]
+pub fn split_scalar_signed_runtime(scalar: &[u32; 8], window_size: usize, digits: &mut [i32]) {
+    debug_assert!((1..=MAX_RUNTIME_SIGNED_WINDOW_SIZE).contains(&window_size));
+    let half = 1i64 << (window_size - 1);
+    let mut carry = 0i64;
+    for (i, digit) in digits.iter_mut().rev().enumerate() {
+        let d = extract_window(scalar, i * window_size, window_size) as i64 + carry;
+        // Same branch-free carry as the generated splitters: 1 iff d > half.
+        carry = ((half - d) >> 63) & 1;
+        *digit = (d - (carry << window_size)) as i32;
+    }
+}
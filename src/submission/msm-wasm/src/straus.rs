@@ -0,0 +1,67 @@
+//! MSM for small inputs.
+//!
+//! Pippenger's algorithm pays for `2^w` buckets per window regardless of the
+//! number of points, which dominates when there are only a few points. Below
+//! `SMALL_MSM_THRESHOLD` points, we use Straus' algorithm instead: every point
+//! gets a table of its first `2^STRAUS_WINDOW_SIZE - 1` multiples, and all
+//! scalars are processed together from the most significant window, sharing a
+//! single doubling chain. This costs about `256 / STRAUS_WINDOW_SIZE + 14`
+//! additions per point and 256 doublings in total.
+
+use rayon::prelude::*;
+
+use crate::MsmCurve;
+
+/// Number of points below which `msm_straus` is used instead of Pippenger's
+/// algorithm.
+pub(crate) const SMALL_MSM_THRESHOLD: usize = 64;
+
+const STRAUS_WINDOW_SIZE: usize = 4;
+const STRAUS_TABLE_SIZE: usize = (1 << STRAUS_WINDOW_SIZE) - 1;
+
+/// Computes `sum_i scalars[i] * points[i]` with Straus' algorithm. The points
+/// are split evenly across threads, each with its own doubling chain. Returns
+/// the identity if there are no points.
+pub(crate) fn msm_straus<G: MsmCurve>(scalars_flat: &[u32], points: &[G]) -> G {
+    debug_assert_eq!(scalars_flat.len(), 8 * points.len());
+    if points.is_empty() {
+        return G::zero();
+    }
+    let chunk_size = points.len().div_ceil(rayon::current_num_threads());
+    scalars_flat
+        .par_chunks(8 * chunk_size)
+        .zip(points.par_chunks(chunk_size))
+        .map(|(scalars_flat, points)| straus_chunk(scalars_flat, points))
+        .sum()
+}
+
+fn straus_chunk<G: MsmCurve>(scalars_flat: &[u32], points: &[G]) -> G {
+    // `tables[i * STRAUS_TABLE_SIZE + k] = (k + 1) * points[i]`.
+    let mut tables = Vec::with_capacity(points.len() * STRAUS_TABLE_SIZE);
+    for point in points {
+        let mut multiple = *point;
+        tables.push(multiple);
+        for _ in 1..STRAUS_TABLE_SIZE {
+            multiple += point;
+            tables.push(multiple);
+        }
+    }
+    let tables = G::normalize_batch(&tables);
+
+    let mut sum = G::zero();
+    // Scalars are big-endian, so the limbs are already in the order we need.
+    for limb in 0..8 {
+        for shift in (0..32).step_by(STRAUS_WINDOW_SIZE).rev() {
+            for _ in 0..STRAUS_WINDOW_SIZE {
+                sum.double_in_place();
+            }
+            for (i, scalar) in scalars_flat.chunks_exact(8).enumerate() {
+                let digit = (scalar[limb] >> shift) as usize & STRAUS_TABLE_SIZE;
+                if digit != 0 {
+                    sum += tables[i * STRAUS_TABLE_SIZE + digit - 1];
+                }
+            }
+        }
+    }
+    sum
+}
@@ -0,0 +1,449 @@
+//! Reads and writes the benchmark workloads under `public/test-data`.
+//!
+//! `points/{k}-power-points.txt` holds `2^k` Edwards BLS12-377 points, one JSON
+//! object per line with the extended coordinates as decimal strings, e.g.,
+//! `{"x":"1","y":"2","t":"3","z":"4"}`. `scalars/{k}-power-scalars.txt` holds
+//! one decimal scalar per line. Scalars are returned as flat big-endian `u32`
+//! limbs, the same layout `msm_end_to_end` takes.
+//!
+//! The files in the repository are git LFS pointers; run `git lfs pull` to
+//! fetch the actual data.
+//!
+//! Larger workloads, and ones with other scalar distributions, can be made with
+//! the `gen-test-data` binary. Their scalars go to
+//! `scalars/{k}-power-{distribution}-scalars.txt`, and the expected result to
+//! `expected/{k}-power[-{distribution}]-expected.txt` as `{"x":"..","y":".."}`.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use ark_ec::{CurveGroup, VariableBaseMSM};
+use ark_ed_on_bls12_377::{EdwardsAffine, EdwardsProjective, Fq, Fr};
+use ark_ff::{PrimeField, Zero};
+use ark_std::rand::{rngs::StdRng, Rng, SeedableRng};
+use ark_std::UniformRand;
+use rayon::prelude::*;
+
+use crate::bytes::{read_fq, write_affine, write_fq, PointEncoding};
+
+/// Sizes (as powers of two) of the workloads in `public/test-data`.
+pub const POWERS: [u32; 5] = [16, 17, 18, 19, 20];
+
+/// Expected `(x, y)` of the MSM of each workload, from `testCases.ts`.
+const EXPECTED_RESULTS: [(u32, &str, &str); 5] = [
+    (
+        16,
+        "4490298471131273381350715833932091894064554978284853693957586604825823442429",
+        "207233051598812890797414182362695316831408959017076683749810755208551572458",
+    ),
+    (
+        17,
+        "405755281347735151880827575059343698498813029460786026451708154294960743560",
+        "7112985356832152643523650125935205310677117771129806490701829425450717492869",
+    ),
+    (
+        18,
+        "4020134989704514076121556080357844499902614818105934254331815581426895427831",
+        "2694327822589008080344499645494473764166611881342421427746308662023437975766",
+    ),
+    (
+        19,
+        "3856727778963570638772781884183843350150969534777451295534564482755471873113",
+        "1398750101296346671684024297455637342909036274728274942667983346895370713922",
+    ),
+    (
+        20,
+        "5201851187583570844529445080011852189038251929148722905178398320328749074909",
+        "3586360219804356686204324370397321114669962278596135149389460948678051407803",
+    ),
+];
+
+const LFS_POINTER_PREFIX: &str = "version https://git-lfs";
+
+/// Returns `public/test-data` of this repository.
+pub fn default_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../public/test-data")
+}
+
+pub fn points_path(dir: &Path, power: u32) -> PathBuf {
+    dir.join(format!("points/{}-power-points.txt", power))
+}
+
+pub fn scalars_path(dir: &Path, power: u32) -> PathBuf {
+    dir.join(format!("scalars/{}-power-scalars.txt", power))
+}
+
+/// Same as `scalars_path`, but for scalars from `distribution`.
+pub fn distribution_scalars_path(
+    dir: &Path,
+    power: u32,
+    distribution: ScalarDistribution,
+) -> PathBuf {
+    match distribution {
+        ScalarDistribution::Uniform => scalars_path(dir, power),
+        _ => dir.join(format!(
+            "scalars/{}-power-{}-scalars.txt",
+            power, distribution
+        )),
+    }
+}
+
+pub fn expected_path(dir: &Path, power: u32, distribution: ScalarDistribution) -> PathBuf {
+    match distribution {
+        ScalarDistribution::Uniform => dir.join(format!("expected/{}-power-expected.txt", power)),
+        _ => dir.join(format!(
+            "expected/{}-power-{}-expected.txt",
+            power, distribution
+        )),
+    }
+}
+
+/// Returns the expected result of the `2^power` workload, if it is one of
+/// `POWERS`.
+pub fn expected_result(power: u32) -> Option<EdwardsAffine> {
+    let (_, x, y) = EXPECTED_RESULTS.iter().find(|(p, _, _)| *p == power)?;
+    let x = read_fq::<Fq>(&parse_u256(x)?)?;
+    let y = read_fq::<Fq>(&parse_u256(y)?)?;
+    Some(EdwardsAffine::new_unchecked(x, y))
+}
+
+/// Parses a decimal integer below 2^256 into big-endian `u32` limbs.
+pub fn parse_u256(s: &str) -> Option<[u32; 8]> {
+    if s.is_empty() {
+        return None;
+    }
+    let mut limbs = [0u32; 8];
+    for c in s.chars() {
+        let mut carry = c.to_digit(10)? as u64;
+        for limb in limbs.iter_mut().rev() {
+            let value = *limb as u64 * 10 + carry;
+            *limb = value as u32;
+            carry = value >> 32;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    Some(limbs)
+}
+
+/// Formats big-endian `u32` limbs as a decimal integer.
+pub fn format_u256(limbs: &[u32]) -> String {
+    let mut limbs = limbs.to_vec();
+    // Base 10^9 digits, least significant first.
+    let mut digits = Vec::new();
+    while limbs.iter().any(|&limb| limb != 0) {
+        let mut remainder = 0u64;
+        for limb in limbs.iter_mut() {
+            let value = (remainder << 32) | *limb as u64;
+            *limb = (value / 1_000_000_000) as u32;
+            remainder = value % 1_000_000_000;
+        }
+        digits.push(remainder as u32);
+    }
+    let mut result = digits.pop().unwrap_or(0).to_string();
+    for digit in digits.iter().rev() {
+        write!(result, "{:09}", digit).unwrap();
+    }
+    result
+}
+
+fn invalid_data(line: usize, message: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line + 1, message),
+    )
+}
+
+/// Calls `f` on every non-empty line, failing early on git LFS pointers.
+fn for_each_line(
+    reader: impl BufRead,
+    mut f: impl FnMut(usize, &str) -> io::Result<()>,
+) -> io::Result<()> {
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if i == 0 && line.starts_with(LFS_POINTER_PREFIX) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "file is a git LFS pointer; run `git lfs pull` to fetch it",
+            ));
+        }
+        if !line.is_empty() {
+            f(i, line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Parses a JSON object with exactly the given keys and decimal string values,
+/// e.g., `{"x":"..","y":".."}`, into the values' limbs in the order of `keys`.
+fn parse_object(line: &str, keys: &[&str]) -> Option<Vec<u32>> {
+    let body = line.strip_prefix('{')?.strip_suffix('}')?;
+    let mut buf = vec![0u32; 8 * keys.len()];
+    let mut seen = vec![false; keys.len()];
+    for field in body.split(',') {
+        let (key, value) = field.split_once(':')?;
+        let key = key.trim().strip_prefix('"')?.strip_suffix('"')?;
+        let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+        let index = keys.iter().position(|&k| k == key)?;
+        buf[8 * index..8 * index + 8].copy_from_slice(&parse_u256(value)?);
+        seen[index] = true;
+    }
+    seen.iter().all(|&s| s).then_some(buf)
+}
+
+fn format_object(buf: &[u32], keys: &[&str]) -> String {
+    let fields = keys
+        .iter()
+        .zip(buf.chunks_exact(8))
+        .map(|(key, value)| format!(r#""{}":"{}""#, key, format_u256(value)))
+        .collect::<Vec<_>>();
+    format!("{{{}}}", fields.join(","))
+}
+
+pub fn read_points(reader: impl BufRead) -> io::Result<Vec<EdwardsProjective>> {
+    let mut points = Vec::new();
+    for_each_line(reader, |i, line| {
+        let buf = parse_object(line, &["x", "y", "t", "z"])
+            .ok_or_else(|| invalid_data(i, "expected a point with decimal x, y, t and z"))?;
+        let point = EdwardsProjective::read_point(&buf)
+            .ok_or_else(|| invalid_data(i, "coordinate is not a canonical field element"))?;
+        points.push(point);
+        Ok(())
+    })?;
+    Ok(points)
+}
+
+pub fn read_scalars(reader: impl BufRead) -> io::Result<Vec<u32>> {
+    let mut scalars_flat = Vec::new();
+    for_each_line(reader, |i, line| {
+        let scalar = parse_u256(line)
+            .ok_or_else(|| invalid_data(i, "expected a decimal integer below 2^256"))?;
+        scalars_flat.extend_from_slice(&scalar);
+        Ok(())
+    })?;
+    Ok(scalars_flat)
+}
+
+pub fn write_points(mut writer: impl Write, points: &[EdwardsProjective]) -> io::Result<()> {
+    let mut buf = [0u32; 32];
+    for point in points {
+        point.write_point(&mut buf);
+        writeln!(writer, "{}", format_object(&buf, &["x", "y", "t", "z"]))?;
+    }
+    Ok(())
+}
+
+pub fn write_scalars(mut writer: impl Write, scalars_flat: &[u32]) -> io::Result<()> {
+    for scalar in scalars_flat.chunks_exact(8) {
+        writeln!(writer, "{}", format_u256(scalar))?;
+    }
+    Ok(())
+}
+
+/// Reads an expected result, `{"x":"..","y":".."}`.
+pub fn read_expected(reader: impl BufRead) -> io::Result<EdwardsAffine> {
+    let mut result = None;
+    for_each_line(reader, |i, line| {
+        let buf = parse_object(line, &["x", "y"])
+            .ok_or_else(|| invalid_data(i, "expected a point with decimal x and y"))?;
+        let x = read_fq::<Fq>(&buf[0..8]);
+        let y = read_fq::<Fq>(&buf[8..16]);
+        let (Some(x), Some(y), None) = (x, y, result) else {
+            return Err(invalid_data(i, "expected a single affine point"));
+        };
+        result = Some(EdwardsAffine::new_unchecked(x, y));
+        Ok(())
+    })?;
+    result.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing expected result"))
+}
+
+pub fn write_expected(mut writer: impl Write, result: &EdwardsAffine) -> io::Result<()> {
+    let buf = write_affine::<EdwardsProjective>(result);
+    writeln!(writer, "{}", format_object(&buf, &["x", "y"]))
+}
+
+pub fn load_points(path: &Path) -> io::Result<Vec<EdwardsProjective>> {
+    fs::File::open(path)
+        .and_then(|file| read_points(BufReader::new(file)))
+        .map_err(|e| with_path(e, path))
+}
+
+pub fn load_scalars(path: &Path) -> io::Result<Vec<u32>> {
+    fs::File::open(path)
+        .and_then(|file| read_scalars(BufReader::new(file)))
+        .map_err(|e| with_path(e, path))
+}
+
+pub fn save_points(path: &Path, points: &[EdwardsProjective]) -> io::Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    write_points(&mut writer, points)?;
+    writer.flush()
+}
+
+pub fn save_scalars(path: &Path, scalars_flat: &[u32]) -> io::Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    write_scalars(&mut writer, scalars_flat)?;
+    writer.flush()
+}
+
+pub fn load_expected(path: &Path) -> io::Result<EdwardsAffine> {
+    fs::File::open(path)
+        .and_then(|file| read_expected(BufReader::new(file)))
+        .map_err(|e| with_path(e, path))
+}
+
+pub fn save_expected(path: &Path, result: &EdwardsAffine) -> io::Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    write_expected(&mut writer, result)?;
+    writer.flush()
+}
+
+fn with_path(error: io::Error, path: &Path) -> io::Error {
+    io::Error::new(error.kind(), format!("{}: {}", path.display(), error))
+}
+
+/// How `generate_scalars` picks scalars.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalarDistribution {
+    /// Uniformly random field elements, like the workloads in the repository.
+    Uniform,
+    /// Mostly zero; one in `SPARSE_RATIO` scalars is uniformly random.
+    Sparse,
+    /// Uniformly random below `2^bits`.
+    SmallBits(u32),
+    /// Drawn from a pool of `N_REPEATED_SCALARS` uniformly random scalars.
+    Repeated,
+    /// Every scalar is the largest field element, `r - 1`.
+    AllMax,
+}
+
+const SPARSE_RATIO: u32 = 16;
+const N_REPEATED_SCALARS: usize = 16;
+/// Points and scalars are generated in chunks of this size, each from its own
+/// RNG, so that the output depends only on the seed and not on the number of
+/// threads.
+const GENERATION_CHUNK_SIZE: usize = 1 << 12;
+
+impl std::fmt::Display for ScalarDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScalarDistribution::Uniform => write!(f, "uniform"),
+            ScalarDistribution::Sparse => write!(f, "sparse"),
+            ScalarDistribution::SmallBits(bits) => write!(f, "small{}", bits),
+            ScalarDistribution::Repeated => write!(f, "repeated"),
+            ScalarDistribution::AllMax => write!(f, "max"),
+        }
+    }
+}
+
+impl FromStr for ScalarDistribution {
+    type Err = String;
+
+    /// Parses the names printed by `Display`, e.g., `sparse` or `small16`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(ScalarDistribution::Uniform),
+            "sparse" => Ok(ScalarDistribution::Sparse),
+            "repeated" => Ok(ScalarDistribution::Repeated),
+            "max" => Ok(ScalarDistribution::AllMax),
+            _ => s
+                .strip_prefix("small")
+                .and_then(|bits| bits.parse().ok())
+                .filter(|bits| (1..=256).contains(bits))
+                .map(ScalarDistribution::SmallBits)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown distribution `{}`; expected uniform, sparse, smallN (1 <= N <= 256), repeated or max",
+                        s
+                    )
+                }),
+        }
+    }
+}
+
+/// Seeds the RNG of the `index`-th chunk of a generated workload.
+fn chunk_rng(seed: u64, stream: u64, index: usize) -> StdRng {
+    let mut seed_bytes = [0u8; 32];
+    seed_bytes[0..8].copy_from_slice(&seed.to_le_bytes());
+    seed_bytes[8..16].copy_from_slice(&stream.to_le_bytes());
+    seed_bytes[16..24].copy_from_slice(&(index as u64).to_le_bytes());
+    StdRng::from_seed(seed_bytes)
+}
+
+/// Generates `n` uniformly random points in the prime-order subgroup.
+pub fn generate_points(n: usize, seed: u64) -> Vec<EdwardsProjective> {
+    let mut points = vec![EdwardsProjective::zero(); n];
+    points
+        .par_chunks_mut(GENERATION_CHUNK_SIZE)
+        .enumerate()
+        .for_each(|(index, chunk)| {
+            let mut rng = chunk_rng(seed, 0, index);
+            for point in chunk {
+                *point = EdwardsProjective::rand(&mut rng);
+            }
+        });
+    // Use the same representation as the workloads in the repository.
+    EdwardsProjective::normalize_batch(&points)
+        .into_iter()
+        .map(EdwardsProjective::from)
+        .collect()
+}
+
+/// Generates `n` scalars from `distribution`, as flat big-endian limbs.
+pub fn generate_scalars(n: usize, distribution: ScalarDistribution, seed: u64) -> Vec<u32> {
+    let mut pool_rng = chunk_rng(seed, 1, usize::MAX);
+    let pool = (0..N_REPEATED_SCALARS)
+        .map(|_| Fr::rand(&mut pool_rng))
+        .collect::<Vec<_>>();
+    let mut scalars_flat = vec![0u32; 8 * n];
+    scalars_flat
+        .par_chunks_mut(8 * GENERATION_CHUNK_SIZE)
+        .enumerate()
+        .for_each(|(index, chunk)| {
+            let mut rng = chunk_rng(seed, 1, index);
+            for buf in chunk.chunks_exact_mut(8) {
+                let scalar = match distribution {
+                    ScalarDistribution::Uniform => Fr::rand(&mut rng),
+                    ScalarDistribution::Sparse if rng.gen_ratio(1, SPARSE_RATIO) => {
+                        Fr::rand(&mut rng)
+                    }
+                    ScalarDistribution::Sparse => Fr::zero(),
+                    ScalarDistribution::SmallBits(bits) => {
+                        let mut bigint = Fr::rand(&mut rng).into_bigint();
+                        for (i, limb) in bigint.0.iter_mut().enumerate() {
+                            let low_bits = bits.saturating_sub(64 * i as u32).min(64);
+                            *limb &= u64::MAX.checked_shr(64 - low_bits).unwrap_or(0);
+                        }
+                        Fr::from_bigint(bigint).unwrap()
+                    }
+                    ScalarDistribution::Repeated => pool[rng.gen_range(0..pool.len())],
+                    ScalarDistribution::AllMax => -Fr::from(1u64),
+                };
+                write_fq(buf, &scalar);
+            }
+        });
+    scalars_flat
+}
+
+/// Computes the MSM of a workload with arkworks, independently of this crate.
+pub fn compute_expected(points: &[EdwardsProjective], scalars_flat: &[u32]) -> EdwardsAffine {
+    let scalars = scalars_flat
+        .chunks_exact(8)
+        .map(|scalar| {
+            let bytes = scalar
+                .iter()
+                .flat_map(|limb| limb.to_be_bytes())
+                .collect::<Vec<_>>();
+            Fr::from_be_bytes_mod_order(&bytes)
+        })
+        .collect::<Vec<_>>();
+    let bases = EdwardsProjective::normalize_batch(points);
+    EdwardsProjective::msm(&bases, &scalars)
+        .unwrap()
+        .into_affine()
+}
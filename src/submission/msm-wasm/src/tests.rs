@@ -0,0 +1,912 @@
+//! Native tests comparing the CPU pipeline against `ark_ec::VariableBaseMSM`.
+//!
+//! Every compiled window size gets its own test, which runs the unsigned and
+//! signed pipelines with both bucketing strategies on random inputs mixed with
+//! adversarial ones: zero and maximum scalars, repeated points and identity
+//! points. Every splitter is also checked to recombine to the original scalar.
+
+use ark_ec::VariableBaseMSM;
+use ark_ff::{BigInt, BigInteger, PrimeField};
+use ark_std::rand::Rng;
+use ark_std::{UniformRand, Zero};
+use proptest::prelude::*;
+
+use super::*;
+use crate::batch_affine::{BATCH_SIZE, MAX_QUEUE_SIZE};
+use crate::bytes::write_points;
+
+/// Number of points in the tests that go through Pippenger's algorithm.
+const N_POINTS: usize = 2 * SMALL_MSM_THRESHOLD + 17;
+
+fn scalars_to_flat<F: PrimeField>(scalars: &[F]) -> Vec<u32> {
+    let mut flat = Vec::with_capacity(8 * scalars.len());
+    for scalar in scalars {
+        let bigint = scalar.into_bigint();
+        for limb in bigint.as_ref().iter().rev() {
+            flat.push((limb >> 32) as u32);
+            flat.push(*limb as u32);
+        }
+    }
+    flat
+}
+
+/// Reads big-endian 256-bit scalars, reducing them modulo the group order.
+fn flat_to_scalars<F: PrimeField>(scalars_flat: &[u32]) -> Vec<F> {
+    scalars_flat
+        .chunks_exact(8)
+        .map(|scalar| {
+            let bytes = scalar
+                .iter()
+                .rev()
+                .flat_map(|limb| limb.to_le_bytes())
+                .collect::<Vec<_>>();
+            F::from_le_bytes_mod_order(&bytes)
+        })
+        .collect()
+}
+
+fn expected<G: MsmCurve + VariableBaseMSM>(points: &[G], scalars_flat: &[u32]) -> Vec<u32> {
+    let scalars = flat_to_scalars::<G::ScalarField>(scalars_flat);
+    let result = G::msm(&G::normalize_batch(points), &scalars).unwrap();
+    write_affine::<G>(&result.into_affine())
+}
+
+fn identity<G: PointEncoding>() -> Vec<u32> {
+    write_affine::<G>(&G::zero().into_affine())
+}
+
+/// Random points and scalars, with every few entries replaced by an
+/// adversarial one.
+fn mixed_inputs<G: MsmCurve>(n: usize, rng: &mut impl Rng) -> (Vec<G>, Vec<u32>) {
+    let mut points = (0..n).map(|_| G::rand(rng)).collect::<Vec<_>>();
+    let mut scalars = (0..n)
+        .map(|_| G::ScalarField::rand(rng))
+        .collect::<Vec<_>>();
+    let minus_one = -G::ScalarField::from(1u64);
+    for i in 0..n {
+        match i % 8 {
+            1 => points[i] = G::zero(),
+            2 => points[i] = points[0],
+            3 => points[i] = -points[0],
+            4 => scalars[i] = G::ScalarField::from(0u64),
+            5 => scalars[i] = minus_one,
+            _ => {}
+        }
+    }
+    let mut scalars_flat = scalars_to_flat(&scalars);
+    // Unreduced scalars: the largest 256-bit integer, and random ones.
+    for (i, scalar) in scalars_flat.chunks_exact_mut(8).enumerate() {
+        match i % 8 {
+            6 => scalar.fill(u32::MAX),
+            7 => scalar.iter_mut().for_each(|limb| *limb = rng.gen()),
+            _ => {}
+        }
+    }
+    (points, scalars_flat)
+}
+
+fn check_window<G, Split, SplitS>(rng: &mut impl Rng)
+where
+    G: MsmCurve + VariableBaseMSM,
+    Split: SplitImpl,
+    Split::Output: Into<u32> + Copy,
+    [(); Split::N_WINDOWS]: Sized,
+    SplitS: SignedSplitImpl,
+    SplitS::Output: Into<i32> + Copy,
+    [(); SplitS::N_WINDOWS]: Sized,
+{
+    let (points, mixed) = mixed_inputs::<G>(N_POINTS, rng);
+    let points_flat = write_points(&points);
+    let max = vec![u32::MAX; 8 * N_POINTS];
+    let zero = vec![0u32; 8 * N_POINTS];
+
+    for scalars_flat in [&mixed, &max, &zero] {
+        let expected = expected(&points, scalars_flat);
+        for strategy in [BucketStrategy::Projective, BucketStrategy::BatchAffine] {
+            assert_eq!(
+                msm_end_to_end::<G, Split>(scalars_flat, &points_flat, strategy),
+                Ok(expected.clone()),
+                "unsigned, window size {}, {:?}",
+                Split::WINDOW_SIZE,
+                strategy,
+            );
+            assert_eq!(
+                msm_end_to_end_signed::<G, SplitS>(scalars_flat, &points_flat, strategy),
+                Ok(expected.clone()),
+                "signed, window size {}, {:?}",
+                SplitS::WINDOW_SIZE,
+                strategy,
+            );
+        }
+    }
+}
+
+/// Checks `msm_multi` and `msm_multi_signed` on three scalar vectors over the
+/// same `n` points, and on none.
+fn check_multi<G, Split, SplitS>(n: usize, rng: &mut impl Rng)
+where
+    G: MsmCurve + VariableBaseMSM,
+    Split: SplitImpl,
+    Split::Output: Into<u32> + Copy,
+    [(); Split::N_WINDOWS]: Sized,
+    SplitS: SignedSplitImpl,
+    SplitS::Output: Into<i32> + Copy,
+    [(); SplitS::N_WINDOWS]: Sized,
+{
+    let (points, mut scalars_flat) = mixed_inputs::<G>(n, rng);
+    for _ in 0..2 {
+        scalars_flat.extend(mixed_inputs::<G>(n, rng).1);
+    }
+    let expected = scalars_flat
+        .chunks(8 * n)
+        .flat_map(|scalars_flat| expected(&points, scalars_flat))
+        .collect::<Vec<_>>();
+    let bases = Bases::new(points);
+    for strategy in [BucketStrategy::Projective, BucketStrategy::BatchAffine] {
+        assert_eq!(
+            msm_multi::<G, Split>(&scalars_flat, &bases, strategy),
+            Ok(expected.clone()),
+            "unsigned, {} points, {:?}",
+            n,
+            strategy,
+        );
+        assert_eq!(
+            msm_multi_signed::<G, SplitS>(&scalars_flat, &bases, strategy),
+            Ok(expected.clone()),
+            "signed, {} points, {:?}",
+            n,
+            strategy,
+        );
+        assert_eq!(msm_multi::<G, Split>(&[], &bases, strategy), Ok(vec![]));
+        assert_eq!(
+            msm_multi_signed::<G, SplitS>(&[], &bases, strategy),
+            Ok(vec![])
+        );
+    }
+}
+
+/// Checks `inter_bucket_reduce` and `inter_bucket_reduce_last` on buckets
+/// filled the same way as the GPU does.
+fn check_inter_bucket_reduce<G, Split>(rng: &mut impl Rng)
+where
+    G: MsmCurve + VariableBaseMSM,
+    Split: SplitImpl,
+    Split::Output: Into<u32> + Copy,
+    [(); Split::N_WINDOWS]: Sized,
+{
+    let (points, scalars_flat) = mixed_inputs::<G>(N_POINTS, rng);
+    let expected = expected(&points, &scalars_flat);
+    let digits = split::<Split>(&scalars_flat);
+    let buckets = digits
+        .chunks(N_POINTS)
+        .map(|window| bucket_cpu(window, &points, 1 << Split::WINDOW_SIZE))
+        .collect::<Vec<_>>();
+
+    let raw_buckets = write_points(&buckets.concat());
+    assert_eq!(
+        inter_bucket_reduce::<G, Split>(&raw_buckets),
+        Ok(expected.clone())
+    );
+    let bucket_sums = buckets
+        .iter()
+        .map(|bucket| bucket_sum_cpu(bucket.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        inter_bucket_reduce_last::<G, Split>(&write_points(&bucket_sums)),
+        Ok(expected)
+    );
+}
+
+fn check_split<Split, SplitS>(rng: &mut impl Rng)
+where
+    Split: SplitImpl,
+    Split::Output: Into<u32> + Copy,
+    [(); Split::N_WINDOWS]: Sized,
+    SplitS: SignedSplitImpl,
+    SplitS::Output: Into<i32> + Copy,
+    [(); SplitS::N_WINDOWS]: Sized,
+{
+    let mut scalars_flat = (0..8 * N_POINTS).map(|_| rng.gen()).collect::<Vec<u32>>();
+    scalars_flat[..8].fill(u32::MAX);
+    scalars_flat[8..16].fill(0);
+    assert_eq!(
+        Ok(split::<Split>(&scalars_flat)),
+        split_runtime(&scalars_flat, Split::WINDOW_SIZE)
+    );
+    assert_eq!(
+        Ok(split_signed::<SplitS>(&scalars_flat)),
+        split_signed_runtime(&scalars_flat, SplitS::WINDOW_SIZE)
+    );
+}
+
+/// Recombines digits ordered from the most significant window, checking that
+/// every digit is in `range`. Returns `None` if the digits overflow or
+/// describe a negative number.
+fn recombine(
+    digits: impl IntoIterator<Item = i64>,
+    window_size: usize,
+    range: std::ops::RangeInclusive<i64>,
+) -> Option<BigInt<5>> {
+    let mut result = BigInt::<5>::zero();
+    for digit in digits {
+        if !range.contains(&digit) || result.0[4] >> (64 - window_size) != 0 {
+            return None;
+        }
+        result.muln(window_size as u32);
+        let overflow = if digit >= 0 {
+            result.add_with_carry(&BigInt::from(digit as u64))
+        } else {
+            result.sub_with_borrow(&BigInt::from(digit.unsigned_abs()))
+        };
+        if overflow {
+            return None;
+        }
+    }
+    Some(result)
+}
+
+fn scalar_to_bigint(scalar: &[u32; 8]) -> BigInt<5> {
+    let mut result = BigInt::<5>::zero();
+    for i in 0..4 {
+        result.0[i] = (scalar[7 - 2 * i] as u64) | ((scalar[6 - 2 * i] as u64) << 32);
+    }
+    result
+}
+
+fn check_recombine<Split, SplitS>(scalar: &[u32; 8]) -> Result<(), TestCaseError>
+where
+    Split: SplitImpl,
+    Split::Output: Into<u32> + Copy,
+    [(); Split::N_WINDOWS]: Sized,
+    SplitS: SignedSplitImpl,
+    SplitS::Output: Into<i32> + Copy,
+    [(); SplitS::N_WINDOWS]: Sized,
+{
+    let expected = Some(scalar_to_bigint(scalar));
+    let w = Split::WINDOW_SIZE;
+    let digits = Split::split(scalar).map(|digit| digit.into() as i64);
+    prop_assert_eq!(recombine(digits, w, 0..=(1 << w) - 1), expected);
+    let half = 1 << (SplitS::WINDOW_SIZE - 1);
+    let digits = SplitS::split(scalar).map(|digit| digit.into() as i64);
+    prop_assert_eq!(
+        recombine(digits, SplitS::WINDOW_SIZE, -half..=half),
+        expected
+    );
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn split_runtime_recombines(scalar in any::<[u32; 8]>(), w in 1..=MAX_RUNTIME_WINDOW_SIZE) {
+        let expected = Some(scalar_to_bigint(&scalar));
+        let mut digits = vec![0u32; n_runtime_windows(w)];
+        split_scalar_runtime(&scalar, w, &mut digits);
+        let digits = digits.iter().map(|&digit| digit as i64);
+        prop_assert_eq!(recombine(digits, w, 0..=(1 << w) - 1), expected);
+    }
+
+    #[test]
+    fn split_signed_runtime_recombines(scalar in any::<[u32; 8]>(), w in 1..=MAX_RUNTIME_SIGNED_WINDOW_SIZE) {
+        let expected = Some(scalar_to_bigint(&scalar));
+        let mut digits = vec![0i32; n_runtime_signed_windows(w)];
+        split_scalar_signed_runtime(&scalar, w, &mut digits);
+        let half = 1 << (w - 1);
+        let digits = digits.iter().map(|&digit| digit as i64);
+        prop_assert_eq!(recombine(digits, w, -half..=half), expected);
+    }
+}
+
+macro_rules! define_window_tests {
+    ($($w:expr),*) => {
+        $(paste! {
+            #[test]
+            fn [<msm_end_to_end_te_ $w>]() {
+                check_window::<EdwardsProjective, [<Split $w>], [<Split $w S>]>(&mut ark_std::test_rng());
+            }
+
+            #[test]
+            fn [<msm_end_to_end_sw_ $w>]() {
+                check_window::<Bls12_377G1Projective, [<Split $w>], [<Split $w S>]>(&mut ark_std::test_rng());
+            }
+
+            #[test]
+            fn [<split_ $w>]() {
+                check_split::<[<Split $w>], [<Split $w S>]>(&mut ark_std::test_rng());
+            }
+
+            proptest! {
+                #[test]
+                fn [<split_recombines_ $w>](scalar in any::<[u32; 8]>()) {
+                    check_recombine::<[<Split $w>], [<Split $w S>]>(&scalar)?;
+                }
+            }
+        })*
+    };
+}
+
+with_window_sizes!(define_window_tests!());
+
+#[cfg(feature = "window-8")]
+#[test]
+fn inter_bucket_reduce_matches_ark() {
+    let mut rng = ark_std::test_rng();
+    check_inter_bucket_reduce::<EdwardsProjective, Split8>(&mut rng);
+    check_inter_bucket_reduce::<Bls12_377G1Projective, Split8>(&mut rng);
+    assert_eq!(
+        inter_bucket_reduce::<EdwardsProjective, Split8>(&[]),
+        Ok(identity::<EdwardsProjective>())
+    );
+}
+
+#[cfg(feature = "window-8")]
+#[test]
+fn msm_multi_matches_ark() {
+    let mut rng = ark_std::test_rng();
+    for n in [9, N_POINTS] {
+        check_multi::<EdwardsProjective, Split8, Split8S>(n, &mut rng);
+        check_multi::<Bls12_377G1Projective, Split8, Split8S>(n, &mut rng);
+    }
+    let bases = Bases::new(Vec::<EdwardsProjective>::new());
+    assert_eq!(
+        msm_multi::<EdwardsProjective, Split8>(&[], &bases, BucketStrategy::Projective),
+        Err(MsmError::EmptyInput)
+    );
+}
+
+/// Checks `FixedBase` with no, full and partial strides, on fewer and more
+/// points than `Split8S` has windows.
+#[cfg(feature = "window-8")]
+#[test]
+fn fixed_base_matches_ark() {
+    fn check<G: MsmCurve + VariableBaseMSM>(rng: &mut impl Rng) {
+        for n in [0, 9, N_POINTS] {
+            let (points, scalars_flat) = mixed_inputs::<G>(n, rng);
+            let expected = expected(&points, &scalars_flat);
+            for stride in [0, 1, 2, 5, Split8S::N_WINDOWS, Split8S::N_WINDOWS + 1] {
+                let fixed_base = FixedBase::new::<Split8S>(&points, stride);
+                assert_eq!(
+                    fixed_base.msm::<Split8S>(&scalars_flat),
+                    Ok(expected.clone()),
+                    "{} points, stride {}",
+                    n,
+                    stride
+                );
+            }
+        }
+    }
+    let mut rng = ark_std::test_rng();
+    check::<EdwardsProjective>(&mut rng);
+    check::<Bls12_377G1Projective>(&mut rng);
+
+    #[cfg(feature = "window-9")]
+    {
+        let (points, scalars_flat) = mixed_inputs::<EdwardsProjective>(9, &mut rng);
+        assert_eq!(
+            FixedBase::new::<Split8S>(&points, 1).msm::<Split9S>(&scalars_flat),
+            Err(MsmError::WindowSizeMismatch {
+                table: 8,
+                requested: 9
+            })
+        );
+    }
+}
+
+/// Checks the batch-affine strategy with the same scalar for every point, so
+/// that all points of a window go to one bucket. Points that find it busy
+/// overflow the queue and are added projectively.
+#[test]
+fn bucket_overflow_matches_ark() {
+    fn check<G: MsmCurve + VariableBaseMSM>() {
+        let mut rng = ark_std::test_rng();
+        let n = 2 * (BATCH_SIZE + MAX_QUEUE_SIZE);
+        let points = (0..n).map(|_| G::rand(&mut rng)).collect::<Vec<_>>();
+        let scalars_flat = scalars_to_flat(&vec![G::ScalarField::rand(&mut rng); n]);
+        let expected = expected(&points, &scalars_flat);
+        let bases = Bases::new(points);
+        let strategy = BucketStrategy::BatchAffine;
+        assert_eq!(
+            msm_bases_runtime(&scalars_flat, 8, &bases, strategy),
+            Ok(expected.clone())
+        );
+        assert_eq!(
+            msm_bases_signed_runtime(&scalars_flat, 8, &bases, strategy),
+            Ok(expected)
+        );
+    }
+    check::<EdwardsProjective>();
+    check::<Bls12_377G1Projective>();
+}
+
+/// Checks that windows bucketed in several parts, which only happens when
+/// there are more threads than windows, match arkworks.
+#[test]
+fn window_parts_match_ark() {
+    let mut rng = ark_std::test_rng();
+    let (points, scalars_flat) = mixed_inputs::<EdwardsProjective>(1024, &mut rng);
+    let expected = expected(&points, &scalars_flat);
+    let bases = Bases::new(points);
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(8)
+        .build()
+        .unwrap();
+    pool.install(|| {
+        let n_buckets = 1 << 8;
+        let n_parts = n_window_parts(1, bases.points.len(), n_buckets);
+        assert_eq!(n_parts, 4);
+        let digits = &split_runtime(&scalars_flat, 8).unwrap()[..bases.points.len()];
+        let bucket = bucket_cpu(digits, &bases.points, n_buckets);
+        assert_eq!(
+            bucket_window(digits, &bases.points, n_parts, |digits, points| {
+                bucket_cpu(digits, points, n_buckets)
+            }),
+            bucket
+        );
+        assert_eq!(
+            bucket_window(digits, bases.affine(), n_parts, |digits, points| {
+                bucket_batch_affine::<EdwardsProjective, u32>(digits, points, n_buckets)
+            }),
+            bucket
+        );
+        // Each group of a fixed-base MSM is a single window.
+        #[cfg(feature = "window-8")]
+        for stride in [1, 2] {
+            assert_eq!(
+                FixedBase::new::<Split8S>(&bases.points, stride).msm::<Split8S>(&scalars_flat),
+                Ok(expected.clone()),
+                "stride {}",
+                stride
+            );
+        }
+    });
+
+    // With a window size of 8, there are 32 windows of 256 buckets.
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(64)
+        .build()
+        .unwrap();
+    pool.install(|| {
+        assert_eq!(n_window_parts(32, bases.points.len(), 1 << 8), 2);
+        for strategy in [BucketStrategy::Projective, BucketStrategy::BatchAffine] {
+            assert_eq!(
+                msm_bases_runtime(&scalars_flat, 8, &bases, strategy),
+                Ok(expected.clone()),
+                "{:?}",
+                strategy
+            );
+            assert_eq!(
+                msm_bases_signed_runtime(&scalars_flat, 8, &bases, strategy),
+                Ok(expected.clone()),
+                "signed, {:?}",
+                strategy
+            );
+        }
+    });
+}
+
+#[test]
+fn other_curves_match_ark() {
+    fn check<G: MsmCurve + VariableBaseMSM>() {
+        let (points, scalars_flat) = mixed_inputs::<G>(N_POINTS, &mut ark_std::test_rng());
+        let bases = Bases::new(points.clone());
+        assert_eq!(
+            msm_bases_signed_runtime(&scalars_flat, 8, &bases, BucketStrategy::BatchAffine),
+            Ok(expected(&points, &scalars_flat))
+        );
+    }
+    check::<BandersnatchProjective>();
+    check::<BabyJubjubProjective>();
+    check::<Bls12_377G2Projective>();
+    #[cfg(feature = "bn254")]
+    check::<Bn254G1Projective>();
+    #[cfg(feature = "bls12-381")]
+    check::<Bls12_381G1Projective>();
+}
+
+#[test]
+fn small_inputs_match_ark() {
+    let mut rng = ark_std::test_rng();
+    for n in [0, 1, 2, 9, SMALL_MSM_THRESHOLD - 1] {
+        let (points, scalars_flat) = mixed_inputs::<EdwardsProjective>(n, &mut rng);
+        let expected = expected(&points, &scalars_flat);
+        let bases = Bases::new(points);
+        assert_eq!(
+            msm_bases_runtime(&scalars_flat, 8, &bases, BucketStrategy::Projective),
+            Ok(expected.clone())
+        );
+        assert_eq!(
+            msm_bases_signed_runtime(&scalars_flat, 8, &bases, BucketStrategy::BatchAffine),
+            Ok(expected)
+        );
+    }
+    let bases = Bases::new(Vec::<EdwardsProjective>::new());
+    assert_eq!(
+        msm_bases_runtime(&[], 8, &bases, BucketStrategy::Projective),
+        Ok(identity::<EdwardsProjective>())
+    );
+}
+
+#[test]
+fn length_mismatch_is_an_error() {
+    let (points, scalars_flat) =
+        mixed_inputs::<EdwardsProjective>(N_POINTS, &mut ark_std::test_rng());
+    let bases = Bases::new(points);
+    assert_eq!(
+        msm_bases_runtime(&scalars_flat[8..], 8, &bases, BucketStrategy::Projective),
+        Err(MsmError::LengthMismatch {
+            n_scalars: N_POINTS - 1,
+            n_points: N_POINTS
+        })
+    );
+    assert_eq!(
+        msm_bases_runtime(&scalars_flat[1..], 8, &bases, BucketStrategy::Projective),
+        Err(MsmError::InvalidLength {
+            name: "scalars",
+            len: 8 * N_POINTS - 1,
+            element_len: 8
+        })
+    );
+}
+
+#[test]
+fn runtime_window_size_is_capped() {
+    // Also below `SMALL_MSM_THRESHOLD`, where the window size is not used.
+    let mut rng = ark_std::test_rng();
+    for n in [0, 9, N_POINTS] {
+        let (points, scalars_flat) = mixed_inputs::<EdwardsProjective>(n, &mut rng);
+        let bases = Bases::new(points);
+        for window_size in [0, MAX_RUNTIME_MSM_WINDOW_SIZE + 1, 32] {
+            let err = Err(MsmError::UnsupportedRuntimeWindowSize {
+                window_size: window_size as u32,
+                max: MAX_RUNTIME_MSM_WINDOW_SIZE as u32,
+            });
+            for strategy in [BucketStrategy::Projective, BucketStrategy::BatchAffine] {
+                assert_eq!(
+                    msm_bases_runtime(&scalars_flat, window_size, &bases, strategy),
+                    err,
+                    "{} points",
+                    n
+                );
+                assert_eq!(
+                    msm_bases_signed_runtime(&scalars_flat, window_size, &bases, strategy),
+                    err,
+                    "{} points",
+                    n
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn points_round_trip() {
+    fn check<G: MsmCurve>() {
+        let mut rng = ark_std::test_rng();
+        let mut points = (0..100).map(|_| G::rand(&mut rng)).collect::<Vec<_>>();
+        points[7] = G::zero();
+        let decoded = read_points::<G>(&write_points(&points)).unwrap();
+        assert_eq!(decoded, points);
+        let decoded = bytes::read_points_le::<G>(&bytes::write_points_le(&points)).unwrap();
+        assert_eq!(decoded, points);
+    }
+    check::<EdwardsProjective>();
+    check::<BandersnatchProjective>();
+    check::<BabyJubjubProjective>();
+    check::<Bls12_377G1Projective>();
+    check::<Bls12_377G2Projective>();
+}
+
+#[test]
+fn read_points_rejects_bad_input() {
+    let mut rng = ark_std::test_rng();
+    let points = (0..4)
+        .map(|_| EdwardsProjective::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let mut points_flat = write_points(&points);
+    let n_u32s = EdwardsProjective::N_U32S_PER_POINT;
+    assert_eq!(
+        read_points::<EdwardsProjective>(&points_flat[1..]),
+        Err(MsmError::InvalidLength {
+            name: "points",
+            len: 4 * n_u32s - 1,
+            element_len: n_u32s
+        })
+    );
+    // A coordinate of the third point no less than the field modulus.
+    points_flat[2 * n_u32s..2 * n_u32s + 8].fill(u32::MAX);
+    assert_eq!(
+        read_points::<EdwardsProjective>(&points_flat),
+        Err(MsmError::NonCanonicalFieldElement { index: 2 })
+    );
+}
+
+#[test]
+fn validate_points_rejects_invalid_points() {
+    use ark_ec::AffineRepr;
+    use ark_ed_on_bls12_377::{EdwardsAffine, Fq};
+    use validate::InvalidPointReason::*;
+
+    /// Replaces points 5 and 100 of valid points with `first` and `second`,
+    /// and checks that the error is about `first`.
+    fn check<G: MsmCurve>(first: G, second: G, reason: validate::InvalidPointReason) {
+        let mut points = (0..N_POINTS)
+            .map(|_| G::rand(&mut ark_std::test_rng()))
+            .collect::<Vec<_>>();
+        validate::validate_points(&points).unwrap();
+        points[5] = first;
+        points[100] = second;
+        assert_eq!(
+            validate::validate_points(&points),
+            Err(MsmError::InvalidPoint { index: 5, reason })
+        );
+    }
+
+    let mut rng = ark_std::test_rng();
+    let point = EdwardsProjective::rand(&mut rng);
+    let zero_z = EdwardsProjective::new_unchecked(point.x, point.y, point.t, Fq::zero());
+    let inconsistent_t =
+        EdwardsProjective::new_unchecked(point.x, point.y, point.t + Fq::from(1u64), point.z);
+    let (x, y) = (Fq::rand(&mut rng), Fq::rand(&mut rng));
+    let not_on_curve = EdwardsProjective::new_unchecked(x, y, x * y, Fq::from(1u64));
+    // Points from `y` alone are in the prime-order subgroup with probability
+    // 1 / cofactor.
+    let not_in_subgroup = (0..)
+        .filter_map(|_| EdwardsAffine::get_point_from_y_unchecked(Fq::rand(&mut rng), false))
+        .find(|point| !point.is_in_correct_subgroup_assuming_on_curve())
+        .unwrap()
+        .into_group();
+    let invalid = [
+        (zero_z, ZeroZ),
+        (inconsistent_t, InconsistentT),
+        (not_on_curve, NotOnCurve),
+        (not_in_subgroup, NotInSubgroup),
+    ];
+    for (first, reason) in invalid {
+        for (second, _) in invalid {
+            check(first, second, reason);
+        }
+    }
+
+    let point = Bls12_377G1Projective::rand(&mut rng).into_affine();
+    let not_on_curve = Bls12_377G1Projective::new_unchecked(
+        point.x,
+        point.y + ark_bls12_377::Fq::from(1u64),
+        ark_bls12_377::Fq::from(1u64),
+    );
+    let not_in_subgroup = (0..)
+        .filter_map(|_| {
+            ark_bls12_377::G1Affine::get_point_from_x_unchecked(
+                ark_bls12_377::Fq::rand(&mut rng),
+                false,
+            )
+        })
+        .find(|point| !point.is_in_correct_subgroup_assuming_on_curve())
+        .unwrap()
+        .into_group();
+    check(not_on_curve, not_in_subgroup, NotOnCurve);
+    check(not_in_subgroup, not_on_curve, NotInSubgroup);
+}
+
+#[test]
+fn add_affine_matches_ark() {
+    let mut rng = ark_std::test_rng();
+    let a = EdwardsProjective::rand(&mut rng).into_affine();
+    let b = EdwardsProjective::rand(&mut rng).into_affine();
+    let a_flat = write_affine::<EdwardsProjective>(&a);
+    let b_flat = write_affine::<EdwardsProjective>(&b);
+    assert_eq!(
+        add_affine::<EdwardsProjective>(&a_flat, &b_flat),
+        Ok(write_affine::<EdwardsProjective>(&(a + b).into_affine()))
+    );
+    let n_u32s = EdwardsProjective::N_U32S_PER_AFFINE;
+    assert_eq!(
+        add_affine::<EdwardsProjective>(&a_flat, &[a_flat.clone(), b_flat].concat()),
+        Err(MsmError::UnexpectedLength {
+            name: "affine point",
+            len: 2 * n_u32s,
+            expected_len: n_u32s
+        })
+    );
+}
+
+/// Checks that `choose_window_size` picks a compiled window size that does not
+/// shrink as the MSM grows, for every thread count up to 64. Sizes are every
+/// integer up to 2^12, then grow by 1/64 up to 2^24.
+#[test]
+fn window_choice_is_compiled_and_monotonic() {
+    let mut sizes = (1..1 << 12).collect::<Vec<usize>>();
+    let mut n = 1 << 12;
+    while n <= 1 << 24 {
+        sizes.push(n);
+        n += n / 64;
+    }
+    sizes.push(1 << 24);
+    for threads in 1..=64 {
+        let mut previous = 0;
+        for &n in &sizes {
+            let window_size = choose_window_size(n, threads);
+            assert!(
+                WINDOW_SIZES.contains(&window_size),
+                "n = {}, {} threads: {}",
+                n,
+                threads,
+                window_size
+            );
+            assert!(
+                window_size >= previous,
+                "n = {}, {} threads: {} after {}",
+                n,
+                threads,
+                window_size,
+                previous
+            );
+            previous = window_size;
+        }
+    }
+}
+
+#[test]
+fn test_data_round_trip() {
+    let mut rng = ark_std::test_rng();
+    let mut points = (0..100)
+        .map(|_| EdwardsProjective::rand(&mut rng))
+        .collect::<Vec<_>>();
+    points[3] = EdwardsProjective::zero();
+    let mut scalars_flat = (0..8 * 100).map(|_| rng.gen()).collect::<Vec<u32>>();
+    scalars_flat[..8].fill(u32::MAX);
+    scalars_flat[8..16].fill(0);
+
+    let mut buf = Vec::new();
+    test_data::write_points(&mut buf, &points).unwrap();
+    assert_eq!(test_data::read_points(&buf[..]).unwrap(), points);
+    let mut buf = Vec::new();
+    test_data::write_scalars(&mut buf, &scalars_flat).unwrap();
+    assert_eq!(test_data::read_scalars(&buf[..]).unwrap(), scalars_flat);
+
+    assert_eq!(
+        test_data::format_u256(&[u32::MAX; 8]),
+        "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+    );
+    assert_eq!(
+        test_data::parse_u256("1000000000"),
+        Some([0, 0, 0, 0, 0, 0, 0, 1_000_000_000])
+    );
+    assert_eq!(
+        test_data::parse_u256(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+        ),
+        None
+    );
+    for power in test_data::POWERS {
+        let expected = test_data::expected_result(power).unwrap();
+        assert!(expected.is_on_curve());
+        let mut buf = Vec::new();
+        test_data::write_expected(&mut buf, &expected).unwrap();
+        assert_eq!(test_data::read_expected(&buf[..]).unwrap(), expected);
+    }
+    assert!(test_data::read_scalars(&b"12\n-3\n"[..]).is_err());
+    assert!(test_data::read_points(&br#"{"x":"1","y":"2","t":"3"}"#[..]).is_err());
+}
+
+/// Runs the benchmark workloads in `public/test-data`, which need to be
+/// fetched with `git lfs pull` first.
+#[test]
+#[ignore]
+fn test_data_workloads_match_expected() {
+    let dir = test_data::default_dir();
+    for power in test_data::POWERS {
+        let points = test_data::load_points(&test_data::points_path(&dir, power)).unwrap();
+        let scalars_flat = test_data::load_scalars(&test_data::scalars_path(&dir, power)).unwrap();
+        let expected = test_data::expected_result(power).unwrap();
+        assert_eq!(
+            msm_bases_signed_runtime(
+                &scalars_flat,
+                13,
+                &Bases::new(points),
+                BucketStrategy::BatchAffine
+            ),
+            Ok(write_affine::<EdwardsProjective>(&expected)),
+            "2^{} workload",
+            power
+        );
+    }
+}
+
+#[test]
+fn generated_workloads_match_ark() {
+    use test_data::ScalarDistribution;
+
+    let points = test_data::generate_points(N_POINTS, 1);
+    assert_eq!(points, test_data::generate_points(N_POINTS, 1));
+    validate::validate_points(&points).unwrap();
+    for name in ["uniform", "sparse", "small16", "repeated", "max"] {
+        let distribution = name.parse::<ScalarDistribution>().unwrap();
+        assert_eq!(distribution.to_string(), name);
+        let scalars_flat = test_data::generate_scalars(N_POINTS, distribution, 1);
+        assert_eq!(
+            write_affine::<EdwardsProjective>(&test_data::compute_expected(&points, &scalars_flat)),
+            expected(&points, &scalars_flat)
+        );
+    }
+    let small = test_data::generate_scalars(N_POINTS, ScalarDistribution::SmallBits(16), 1);
+    assert!(small
+        .chunks_exact(8)
+        .all(|scalar| scalar[..7].iter().all(|&limb| limb == 0) && scalar[7] < 1 << 16));
+    assert!("small0".parse::<ScalarDistribution>().is_err());
+}
+
+#[test]
+fn timed_msm_matches_ark() {
+    use native::MsmOptions;
+
+    let mut rng = ark_std::test_rng();
+    let (points, scalars_flat) = mixed_inputs::<EdwardsProjective>(N_POINTS, &mut rng);
+    let points_flat = native::points_to_flat(&points);
+    let want = expected(&points, &scalars_flat);
+    // 7 has no compiled splitter, so it goes through the runtime splitter.
+    for window_size in [7, WINDOW_SIZES[0] as usize] {
+        for signed in [false, true] {
+            let options = MsmOptions {
+                window_size,
+                signed,
+                strategy: BucketStrategy::BatchAffine,
+            };
+            let (result, spans) =
+                native::msm_end_to_end_timed(&scalars_flat, &points_flat, options).unwrap();
+            assert_eq!(write_affine::<EdwardsProjective>(&result), want);
+            assert_eq!(span_names(&spans), ["decode", "split", "bucket", "reduce"]);
+            assert!(spans.windows(2).all(|w| w[0].start_ms <= w[1].start_ms));
+            let bucket = &spans[2];
+            let n_windows = if signed {
+                n_runtime_signed_windows(window_size)
+            } else {
+                n_runtime_windows(window_size)
+            };
+            assert_eq!(span_names(&bucket.children), vec!["bucket-sum"; n_windows]);
+            assert!(bucket
+                .children
+                .iter()
+                .all(|child| child.start_ms >= bucket.start_ms && child.duration_ms >= 0.0));
+        }
+    }
+    let (result, spans) = native::msm_end_to_end_timed(
+        &scalars_flat[..8],
+        &points_flat[..EdwardsProjective::N_U32S_PER_POINT],
+        MsmOptions {
+            window_size: 7,
+            signed: false,
+            strategy: BucketStrategy::Projective,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        write_affine::<EdwardsProjective>(&result),
+        expected(&points[..1], &scalars_flat[..8])
+    );
+    assert_eq!(span_names(&spans), ["decode", "straus"]);
+}
+
+fn span_names(spans: &[Span]) -> Vec<&'static str> {
+    spans.iter().map(|span| span.name).collect()
+}
+
+#[test]
+fn spans_nest() {
+    // Spans outside `record` are not kept.
+    assert_eq!(spans::span("outside", || 1), 1);
+    let (result, spans) = spans::record(|| {
+        spans::span("a", || {
+            spans::span("b", || ());
+            let (_, c) = spans::timed("c", || ());
+            spans::attach(vec![c]);
+        });
+        spans::span("d", || 2)
+    });
+    assert_eq!(result, 2);
+    assert_eq!(span_names(&spans), ["a", "d"]);
+    assert_eq!(span_names(&spans[0].children), ["b", "c"]);
+    assert!(spans[0].start_ms >= 0.0);
+    assert!(spans[0].children[0].start_ms >= spans[0].start_ms);
+    assert!(spans[1].children.is_empty());
+    assert!(spans::record(|| ()).1.is_empty());
+}
@@ -12,46 +12,31 @@ pub fn set_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
-#[cfg(target_arch = "wasm32")]
-pub fn time_begin(label: &str) {
-    use web_sys::console;
-    console::time_with_label(label);
-}
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_panic_hook() {}
 
+/// Milliseconds since an arbitrary point in time, which is the same on every
+/// thread. `performance.now()` is relative to the time origin of each worker,
+/// so we add that origin.
 #[cfg(target_arch = "wasm32")]
-pub fn time_end(label: &str) {
-    use web_sys::console;
-    console::time_end_with_label(label);
-}
-
-use std::{
-    collections::HashMap,
-    sync::{Mutex, OnceLock},
-    time::Instant,
-};
+pub fn now_ms() -> f64 {
+    use wasm_bindgen::JsCast;
 
-static START_TIMESTAMP: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
-
-#[cfg(not(target_arch = "wasm32"))]
-pub fn time_begin(label: &str) {
-    let timestamps = START_TIMESTAMP.get_or_init(|| Mutex::new(HashMap::new()));
-    let mut timestamps = timestamps.lock().unwrap();
-    if timestamps.contains_key(label) {
-        panic!("duplicate label: {}", label);
+    thread_local! {
+        static PERFORMANCE: web_sys::Performance =
+            js_sys::Reflect::get(&js_sys::global(), &"performance".into())
+                .unwrap()
+                .unchecked_into();
     }
-    timestamps.insert(label.to_string(), Instant::now());
+    PERFORMANCE.with(|performance| performance.time_origin() + performance.now())
 }
 
+/// Milliseconds since an arbitrary point in time.
 #[cfg(not(target_arch = "wasm32"))]
-pub fn time_end(label: &str) {
-    let timestamps = START_TIMESTAMP.get_or_init(|| Mutex::new(HashMap::new()));
-    let mut timestamps = timestamps.lock().unwrap();
-    match timestamps.get(label) {
-        None => panic!("unknown label: {}", label),
-        Some(start) => {
-            let elapsed = start.elapsed();
-            log::info!("{}: {}ms", label, elapsed.as_millis());
-            timestamps.remove(label);
-        }
-    }
+pub fn now_ms() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
 }
@@ -0,0 +1,99 @@
+//! Validation of input points.
+//!
+//! `read_points` only checks that coordinates are canonical field elements,
+//! and builds points with `new_unchecked`. A point that is not on the curve or
+//! not in the prime-order subgroup silently produces a wrong MSM, so inputs
+//! from untrusted sources should go through `validate_points` first.
+
+use std::fmt;
+
+use ark_ec::{
+    short_weierstrass::{self, SWCurveConfig},
+    twisted_edwards::{self, TECurveConfig},
+    CurveGroup,
+};
+use ark_ff::{Field, Zero};
+use rayon::prelude::*;
+
+use crate::error::MsmError;
+
+/// Why a point failed validation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidPointReason {
+    /// The projective `z` coordinate is zero.
+    ZeroZ,
+    /// The extended coordinates do not satisfy `t * z == x * y`.
+    InconsistentT,
+    /// The point does not satisfy the curve equation.
+    NotOnCurve,
+    /// The point is not in the prime-order subgroup.
+    NotInSubgroup,
+}
+
+impl fmt::Display for InvalidPointReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidPointReason::ZeroZ => write!(f, "z is zero"),
+            InvalidPointReason::InconsistentT => write!(f, "t * z != x * y"),
+            InvalidPointReason::NotOnCurve => write!(f, "not on the curve"),
+            InvalidPointReason::NotInSubgroup => write!(f, "not in the prime-order subgroup"),
+        }
+    }
+}
+
+/// Trait for points that can be checked to be valid inputs to an MSM.
+pub trait ValidatePoint: CurveGroup {
+    fn validate(&self) -> Result<(), InvalidPointReason>;
+}
+
+impl<P: TECurveConfig> ValidatePoint for twisted_edwards::Projective<P> {
+    fn validate(&self) -> Result<(), InvalidPointReason> {
+        if self.z.is_zero() {
+            return Err(InvalidPointReason::ZeroZ);
+        }
+        if self.t * self.z != self.x * self.y {
+            return Err(InvalidPointReason::InconsistentT);
+        }
+        // a x^2 + y^2 = 1 + d x^2 y^2, multiplied through by z^4.
+        let x2 = self.x.square();
+        let y2 = self.y.square();
+        let z2 = self.z.square();
+        if (P::mul_by_a(x2) + y2) * z2 != z2.square() + P::COEFF_D * x2 * y2 {
+            return Err(InvalidPointReason::NotOnCurve);
+        }
+        if !self
+            .into_affine()
+            .is_in_correct_subgroup_assuming_on_curve()
+        {
+            return Err(InvalidPointReason::NotInSubgroup);
+        }
+        Ok(())
+    }
+}
+
+impl<P: SWCurveConfig> ValidatePoint for short_weierstrass::Projective<P> {
+    fn validate(&self) -> Result<(), InvalidPointReason> {
+        let affine = self.into_affine();
+        if !affine.is_on_curve() {
+            return Err(InvalidPointReason::NotOnCurve);
+        }
+        if !affine.is_in_correct_subgroup_assuming_on_curve() {
+            return Err(InvalidPointReason::NotInSubgroup);
+        }
+        Ok(())
+    }
+}
+
+/// Validates `points` in parallel, returning an error for the first invalid
+/// one.
+pub fn validate_points<G: ValidatePoint>(points: &[G]) -> Result<(), MsmError> {
+    match points
+        .par_iter()
+        .enumerate()
+        .map(|(index, point)| (index, point.validate()))
+        .find_first(|(_, result)| result.is_err())
+    {
+        Some((index, Err(reason))) => Err(MsmError::InvalidPoint { index, reason }),
+        _ => Ok(()),
+    }
+}
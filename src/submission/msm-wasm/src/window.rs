@@ -0,0 +1,112 @@
+//! Window size selection.
+//!
+//! The cost of `msm_end_to_end` is estimated for every compiled window size
+//! by counting the point additions and doublings on the critical path, given
+//! how the work is spread over threads (see `bucket_windows`). The relative
+//! costs of an addition and a doubling default to rough figures, and can be
+//! measured on the running machine with `calibrate_window_size_model`.
+
+use std::sync::Mutex;
+
+use ark_ec::Group;
+use ark_ff::Zero;
+use wasm_bindgen::prelude::*;
+
+use crate::utils::now_ms;
+use crate::{EdwardsProjective, BUCKET_SUM_LEAF_SIZE, WINDOW_SIZES};
+
+#[derive(Clone, Copy, Debug)]
+struct CostModel {
+    /// Cost of adding a point to a bucket.
+    add: f64,
+    /// Cost of doubling a point.
+    double: f64,
+}
+
+static COST_MODEL: Mutex<CostModel> = Mutex::new(CostModel {
+    add: 1.0,
+    double: 0.8,
+});
+
+/// Number of operations timed for each kind by `calibrate_window_size_model`.
+const N_CALIBRATION_OPS: usize = 1 << 14;
+
+/// Estimates the cost of an MSM of size `n` with the given window size.
+fn estimate_cost(model: &CostModel, n: usize, threads: usize, window_size: usize) -> f64 {
+    let threads = threads.max(1);
+    let n_windows = 256usize.div_ceil(window_size);
+    let n_buckets = 1usize << window_size;
+    let n_parts = if n_windows >= threads {
+        1
+    } else {
+        threads.div_ceil(n_windows).min(n / n_buckets).max(1)
+    };
+    // Windows are processed `threads` at a time. When there are fewer windows
+    // than threads, each window is split into parts whose buckets are merged
+    // pairwise, and the spare threads help with the running sum.
+    let rounds = n_windows.div_ceil(threads);
+    let threads_per_window = (threads / n_windows).max(1);
+    let merge_depth = n_parts.next_power_of_two().trailing_zeros() as usize;
+    let bucketing = n.div_ceil(n_parts) + merge_depth * n_buckets;
+    let running_sum =
+        2 * n_buckets / threads_per_window.min(n_buckets.div_ceil(BUCKET_SUM_LEAF_SIZE));
+    let window_adds = rounds * (bucketing + running_sum);
+    // `reduce_last` is sequential.
+    let last_adds = n_windows;
+    let last_doubles = n_windows * window_size;
+    model.add * (window_adds + last_adds) as f64 + model.double * last_doubles as f64
+}
+
+/// Picks the compiled window size with the lowest estimated cost for an MSM of
+/// size `n` on `threads` threads.
+#[wasm_bindgen]
+pub fn choose_window_size(n: usize, threads: usize) -> u32 {
+    let model = *COST_MODEL.lock().unwrap();
+    let mut best = WINDOW_SIZES[0];
+    let mut best_cost = f64::INFINITY;
+    for &window_size in WINDOW_SIZES {
+        let cost = estimate_cost(&model, n, threads, window_size as usize);
+        if cost < best_cost {
+            best = window_size;
+            best_cost = cost;
+        }
+    }
+    best
+}
+
+/// Measures the costs of point additions and doublings on this machine and
+/// uses them for later calls to `choose_window_size`. Takes a few tens of
+/// milliseconds.
+#[wasm_bindgen]
+pub fn calibrate_window_size_model() {
+    let g = EdwardsProjective::generator();
+    let mut points = vec![g; N_CALIBRATION_OPS];
+    for i in 1..N_CALIBRATION_OPS {
+        points[i] = points[i - 1] + g;
+    }
+
+    let start = now_ms();
+    let mut sum = EdwardsProjective::zero();
+    for point in &points {
+        sum += point;
+    }
+    let add = now_ms() - start;
+
+    let start = now_ms();
+    for _ in 0..N_CALIBRATION_OPS {
+        sum.double_in_place();
+    }
+    let double = now_ms() - start;
+
+    // Keep the computation from being optimized away.
+    if sum.is_zero() {
+        log::info!("calibration sum is zero");
+    }
+    if add > 0.0 && double > 0.0 {
+        *COST_MODEL.lock().unwrap() = CostModel {
+            add: 1.0,
+            double: double / add,
+        };
+    }
+    log::info!("window size model: add {}ms, double {}ms", add, double);
+}